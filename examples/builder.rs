@@ -0,0 +1,20 @@
+extern crate tini;
+use tini::Ini;
+
+fn main() {
+    let debug = std::env::args().any(|arg| arg == "--debug");
+    let workers = [1, 2, 4];
+
+    // builder methods take `&mut self`, so there is no need to reassign on every step
+    let mut builder = Ini::builder();
+    builder.section("server").item("host", "localhost");
+    if debug {
+        builder.item("log_level", "debug");
+    }
+    for (i, count) in workers.iter().enumerate() {
+        builder.item(format!("worker{}", i), count.to_string());
+    }
+    let config = builder.build();
+
+    println!("{}", config.to_buffer());
+}
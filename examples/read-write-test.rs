@@ -1,8 +1,11 @@
 extern crate tini;
 use tini::Ini;
 
+#[allow(clippy::redundant_static_lifetimes)]
 static INPUT: &'static str = "./examples/example.ini";
+#[allow(clippy::redundant_static_lifetimes)]
 static OUTPUT: &'static str = "./examples/test.ini";
+#[allow(clippy::redundant_static_lifetimes)]
 static SPLIT: &'static str = "=----------------------------------------------------------=";
 
 fn main() {
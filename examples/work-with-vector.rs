@@ -1,6 +1,7 @@
 extern crate tini;
 use tini::Ini;
 
+#[allow(clippy::redundant_static_lifetimes)]
 static INPUT: &'static str = "./examples/example.ini";
 
 fn main() {
@@ -4,7 +4,7 @@
 //!
 //! Features:
 //!
-//! * no dependencies;
+//! * no dependencies by default;
 //! * parsing [from file](struct.Ini.html#method.from_file) and [from buffer](struct.Ini.html#method.from_buffer);
 //! * [convert parsed value to given type](struct.Ini.html#method.get);
 //! * [parse comma-separated lists to vectors](struct.Ini.html#method.get_vec);
@@ -38,22 +38,38 @@
 //! assert_eq!(consts, [3.1416, 2.7183]);
 //! assert_eq!(lost, [4, 8, 15, 16, 23, 42]);
 //! ````
-mod ordered_hashmap;
+pub mod ordered_hashmap;
 mod parser;
 
-use ordered_hashmap::OrderedHashMap;
-use parser::{parse_line, Parsed};
+pub use ordered_hashmap::OrderedHashMap;
+use parser::{
+    escape, escape_element, escape_key, escaped_len, inline_comment, parse_indented_line,
+    parse_line, quote_level, split_csv, split_json_array, split_unescaped, unescape,
+    unescape_element, Parsed,
+};
+pub use parser::{ParseError, ParseOptions};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hash;
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::iter::Iterator;
-use std::path::Path;
+use std::net::{IpAddr, SocketAddr};
+use std::num::ParseIntError;
+use std::ops::Index;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 type Section = OrderedHashMap<String, String>;
 type IniParsed = OrderedHashMap<String, Section>;
 type SectionIter<'a> = ordered_hashmap::Iter<'a, String, String>;
 type SectionIterMut<'a> = ordered_hashmap::IterMut<'a, String, String>;
+type IncludeHandler<'a, E> = &'a mut dyn FnMut(&str) -> Result<Ini, E>;
 
 /// Structure for INI-file data
 #[derive(Debug)]
@@ -61,6 +77,135 @@ pub struct Ini {
     #[doc(hidden)]
     data: IniParsed,
     last_section_name: String,
+    vec_formats: HashMap<(String, String), VecFormat>,
+    comments: HashMap<(String, String), String>,
+    section_comments: HashMap<String, String>,
+    inline_comments: HashMap<(String, String), String>,
+    escape_values: bool,
+    line_ending: LineEnding,
+    section_header: fn(&str) -> String,
+    merged_sections: Vec<String>,
+    modified: bool,
+    parse_errors: bool,
+}
+
+/// Format of a list value added with [`item_vec_with_sep()`](struct.Ini.html#method.item_vec_with_sep)
+///
+/// Ini remembers the separator of such values, so [`get_vec()`](struct.Ini.html#method.get_vec)
+/// can split them without specifying the separator again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VecFormat {
+    sep: String,
+}
+
+/// Line break written by [`line_ending()`](struct.Ini.html#method.line_ending)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    /// `\n`, the default
+    Lf,
+    /// `\r\n`, as expected by some Windows tools
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Error returned by [`try_from_file()`](struct.Ini.html#method.try_from_file)
+#[derive(Debug)]
+pub enum IniError {
+    /// File can not be read
+    Io(io::Error),
+    /// File contents are malformed
+    Parse(ParseError),
+    /// File includes itself directly or indirectly
+    IncludeCycle(PathBuf),
+}
+
+impl fmt::Display for IniError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IniError::Io(err) => write!(f, "{}", err),
+            IniError::Parse(err) => write!(f, "{}", err),
+            IniError::IncludeCycle(path) => write!(f, "include cycle at {}", path.display()),
+        }
+    }
+}
+
+impl error::Error for IniError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            IniError::Io(err) => Some(err),
+            IniError::Parse(err) => Some(err),
+            IniError::IncludeCycle(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for IniError {
+    fn from(err: io::Error) -> Self {
+        IniError::Io(err)
+    }
+}
+
+impl From<ParseError> for IniError {
+    fn from(err: ParseError) -> Self {
+        IniError::Parse(err)
+    }
+}
+
+/// Error returned by [`get_interpolated()`](struct.Ini.html#method.get_interpolated)
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpolationError {
+    /// Referenced key doesn't exist, holds section name and key
+    Missing(String, String),
+    /// Value refers to itself directly or indirectly, holds section name and key
+    Cycle(String, String),
+    /// Resolved value exceeds the length limit, holds section name and key
+    TooLong(String, String),
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpolationError::Missing(section, key) => {
+                write!(f, "missing reference {}:{}", section, key)
+            }
+            InterpolationError::Cycle(section, key) => {
+                write!(f, "reference cycle at {}:{}", section, key)
+            }
+            InterpolationError::TooLong(section, key) => {
+                write!(f, "resolved value too long at {}:{}", section, key)
+            }
+        }
+    }
+}
+
+impl error::Error for InterpolationError {}
+
+/// Error returned by [`require_section()`](struct.Ini.html#method.require_section), holds
+/// section name
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingSection(pub String);
+
+impl fmt::Display for MissingSection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing required section {}", self.0)
+    }
+}
+
+impl error::Error for MissingSection {}
+
+impl VecFormat {
+    /// Separator used to join the list elements
+    pub fn separator(&self) -> &str {
+        &self.sep
+    }
 }
 
 impl Ini {
@@ -69,22 +214,362 @@ impl Ini {
         Ini {
             data: IniParsed::new(),
             last_section_name: String::new(),
+            vec_formats: HashMap::new(),
+            comments: HashMap::new(),
+            section_comments: HashMap::new(),
+            inline_comments: HashMap::new(),
+            escape_values: false,
+            line_ending: LineEnding::Lf,
+            section_header: |name| format!("[{}]", name),
+            merged_sections: Vec::new(),
+            modified: false,
+            parse_errors: false,
         }
     }
 
+    /// Create a builder which constructs Ini through mutable references
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let verbose = true;
+    /// let mut builder = Ini::builder();
+    /// builder.section("log").item("level", "info");
+    /// if verbose {
+    ///     builder.item("level", "debug");
+    /// }
+    /// let conf = builder.build();
+    /// let level: String = conf.get("log", "level").unwrap();
+    /// assert_eq!(level, "debug");
+    /// ```
+    pub fn builder() -> IniBuilder {
+        IniBuilder::new()
+    }
+
     fn from_string(string: &str) -> Ini {
+        Ini::from_lines(string.lines())
+    }
+
+    /// Construct Ini from lines
+    ///
+    /// Useful with line readers like `BufRead::lines()`, which would otherwise require joining
+    /// lines back into a buffer for [`from_buffer()`](#method.from_buffer).
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let lines = vec!["[section]", "one = 1"];
+    /// let conf = Ini::from_lines(lines);
+    /// let value: Option<u8> = conf.get("section", "one");
+    /// assert_eq!(value, Some(1));
+    /// ```
+    pub fn from_lines<I, S>(lines: I) -> Ini
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let options = ParseOptions::default();
+        let result = Ini::parse(
+            lines,
+            &options,
+            |err| {
+                // lines are counted from 0 in this output
+                println!("line {}: error: {}", err.line() - 1, err.message());
+                Ok::<(), ParseError>(())
+            },
+            None,
+        );
+        // errors are never propagated from the handler above
+        result.unwrap_or_default()
+    }
+
+    fn from_string_with_options(string: &str, options: &ParseOptions) -> Result<Ini, ParseError> {
+        Ini::parse(string.lines(), options, Err, None)
+    }
+
+    /// Parse `lines` passing every error to `on_error`, which decides whether to abort, and
+    /// every include directive to `on_include`, if any
+    fn parse<E, F, I, S>(
+        lines: I,
+        options: &ParseOptions,
+        mut on_error: F,
+        mut on_include: Option<IncludeHandler<E>>,
+    ) -> Result<Ini, E>
+    where
+        F: FnMut(ParseError) -> Result<(), E>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
         let mut result = Ini::new();
-        for (i, line) in string.lines().enumerate() {
-            match parse_line(&line) {
-                Parsed::Section(name) => result = result.section(name),
-                Parsed::Value(name, value) => result = result.item(name, value),
-                Parsed::Error(msg) => println!("line {}: error: {}", i, msg),
-                _ => (),
+        let mut errors = false;
+        let mut on_error = |err| {
+            errors = true;
+            on_error(err)
+        };
+        let mut has_section = false;
+        let mut seen_sections = HashSet::new();
+        // comment lines directly preceding a key
+        let mut comment: Option<String> = None;
+        // key of the current block with `indented_keys`
+        let mut parent: Option<String> = None;
+        for (i, line) in lines.into_iter().enumerate() {
+            if options
+                .max_line_length
+                .map_or(false, |max| line.as_ref().len() > max)
+            {
+                comment = None;
+                on_error(ParseError::new(i + 1, "line too long"))?;
+                continue;
+            }
+            let parsed = if options.indented_keys {
+                parse_indented_line(line.as_ref(), &mut parent, options)
+            } else {
+                parse_line(line.as_ref(), options)
+            };
+            match parsed {
+                Parsed::Section(name) => {
+                    let text = comment.take();
+                    let is_new = seen_sections.insert(name.clone());
+                    if is_new
+                        && options
+                            .max_sections
+                            .map_or(false, |max| seen_sections.len() > max)
+                    {
+                        seen_sections.remove(&name);
+                        on_error(ParseError::new(i + 1, "too many sections"))?;
+                        continue;
+                    }
+                    has_section = true;
+                    if let Some(text) = text {
+                        result.section_comments.entry(name.clone()).or_insert(text);
+                    }
+                    if options.dedup_sections && !is_new && !result.merged_sections.contains(&name)
+                    {
+                        result.merged_sections.push(name.clone());
+                    }
+                    result = result.section(name);
+                }
+                Parsed::Value(name, value) => {
+                    if options.require_section_header && !has_section {
+                        comment = None;
+                        on_error(ParseError::new(i + 1, "key before any section header"))?;
+                        continue;
+                    }
+                    if options.treat_blank_as_missing && value.trim().is_empty() {
+                        comment = None;
+                        continue;
+                    }
+                    let value = if options.unescape_values {
+                        unescape(&value)
+                    } else {
+                        value
+                    };
+                    if let (Some(key), Some(include)) = (&options.include_key, &mut on_include) {
+                        if name == *key {
+                            comment = None;
+                            let mut included = include(&value)?;
+                            // keys before the first header belong to the including section
+                            let current = result.last_section_name.clone();
+                            included.rename_sections(|name| match name {
+                                "" => current.clone(),
+                                name => name.to_owned(),
+                            });
+                            result.merge(&included);
+                            continue;
+                        }
+                    }
+                    if let Some(max) = options.max_keys_per_section {
+                        let section = result.data.get(&result.last_section_name);
+                        if section.map_or(0, |x| x.len()) >= max
+                            && !section.map_or(false, |x| x.contains_key(&name))
+                        {
+                            comment = None;
+                            on_error(ParseError::new(i + 1, "too many keys in section"))?;
+                            continue;
+                        }
+                    }
+                    if let Some(text) = comment.take() {
+                        let section = result.last_section_name.clone();
+                        result.comments.insert((section, name.clone()), text);
+                    }
+                    if options.inline_comments {
+                        if let Some(text) = inline_comment(line.as_ref()) {
+                            let section = result.last_section_name.clone();
+                            result.inline_comments.insert((section, name.clone()), text);
+                        }
+                    }
+                    result = result.item(name, value);
+                }
+                // empty comment lines carry no text
+                Parsed::Comment(text) if text.is_empty() => (),
+                Parsed::Comment(text) => match comment {
+                    Some(ref mut lines) => {
+                        lines.push('\n');
+                        lines.push_str(&text);
+                    }
+                    None => comment = Some(text),
+                },
+                Parsed::Error(msg) => {
+                    comment = None;
+                    on_error(ParseError::new(i + 1, msg))?;
+                }
+                Parsed::Empty => comment = None,
             };
         }
+        result.modified = false;
+        result.parse_errors = errors;
+        Ok(result)
+    }
+
+    /// Read file at `path`, following includes. `chain` holds canonical paths of the files
+    /// being read, to detect include cycles.
+    fn load_file(
+        path: &Path,
+        options: &ParseOptions,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<Ini, IniError> {
+        let file = File::open(path)?;
+        let canonical = path.canonicalize()?;
+        if chain.contains(&canonical) {
+            return Err(IniError::IncludeCycle(path.to_owned()));
+        }
+        let mut reader = BufReader::new(file);
+        let mut buffer = String::new();
+        reader.read_to_string(&mut buffer)?;
+
+        let dir = path.parent().map(Path::to_owned).unwrap_or_default();
+        chain.push(canonical);
+        let result = Ini::parse(
+            buffer.lines(),
+            options,
+            |err| Err(IniError::Parse(err)),
+            Some(&mut |include: &str| Ini::load_file(&dir.join(include), options, chain)),
+        );
+        chain.pop();
+        result
+    }
+
+    /// Add all sections and keys of `other`, replacing values of existing keys
+    fn merge(&mut self, other: &Ini) {
+        self.modified = true;
+        for (section, entries) in other.data.iter() {
+            let target = self.data.entry(section.clone()).or_default();
+            for (key, value) in entries {
+                target.insert(key.clone(), value.clone());
+                self.vec_formats.remove(&(section.clone(), key.clone()));
+            }
+        }
+        self.vec_formats.extend(other.vec_formats.clone());
+        self.comments.extend(other.comments.clone());
+        self.section_comments.extend(other.section_comments.clone());
+        self.inline_comments.extend(other.inline_comments.clone());
+    }
+
+    /// Concatenate configs into a new Ini
+    ///
+    /// Sections are appended in order of appearance. Sections with the same name are joined
+    /// into one at the position of the first occurrence; if a key occurs in several of them, the
+    /// value from the last config wins.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let a = Ini::from_buffer("[a]\nx = 1");
+    /// let b = Ini::from_buffer("[b]\ny = 2\n[a]\nx = 3");
+    /// let conf = Ini::concat(&[&a, &b]);
+    /// assert_eq!(conf.to_buffer(), "[a]\nx = 3\n\n[b]\ny = 2");
+    /// ```
+    pub fn concat(configs: &[&Ini]) -> Ini {
+        let mut result = Ini::new();
+        for config in configs {
+            result.merge(config);
+        }
+        result.modified = false;
         result
     }
 
+    /// Add keys of `section` from `other`, replacing values of existing keys
+    ///
+    /// Replaced keys keep their position, new keys are appended to the section, and the section
+    /// itself is appended if `self` lacks it. Comments and vector formats of the section are
+    /// taken over as well. Other sections are left untouched; nothing happens if `other` has no
+    /// such section.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[db]\nhost = localhost\nport = 5432\n[log]\nlevel = info");
+    /// let other = Ini::from_buffer("[db]\nport = 6432\nuser = app\n[log]\nlevel = debug");
+    /// conf.merge_section(&other, "db");
+    /// assert_eq!(
+    ///     conf.to_buffer(),
+    ///     "[db]\nhost = localhost\nport = 6432\nuser = app\n\n[log]\nlevel = info"
+    /// );
+    /// ```
+    pub fn merge_section(&mut self, other: &Ini, section: &str) {
+        if let Some(part) = other.extract_section(section) {
+            self.merge(&part);
+        }
+    }
+
+    /// Names of sections defined more than once in the parsed input, in order of their first
+    /// redefinition
+    ///
+    /// Keys of such sections are merged into one section. The list is only filled if
+    /// [`dedup_sections`](struct.ParseOptions.html#method.dedup_sections) is set.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, ParseOptions};
+    /// let options = ParseOptions::new().dedup_sections(true);
+    /// let conf = Ini::from_buffer_with_options("[a]\nx = 1\n[b]\n[a]\ny = 2", &options).unwrap();
+    /// assert_eq!(conf.merged_sections(), ["a"]);
+    /// assert_eq!(conf.to_buffer(), "[a]\nx = 1\ny = 2");
+    /// ```
+    pub fn merged_sections(&self) -> &[String] {
+        &self.merged_sections
+    }
+
+    /// Check if malformed lines were skipped while parsing, e.g. by
+    /// [`from_buffer()`](#method.from_buffer)
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\none = 1\nbad");
+    /// assert!(conf.had_parse_errors());
+    /// let conf = Ini::from_buffer("[section]\none = 1");
+    /// assert!(!conf.had_parse_errors());
+    /// ```
+    pub fn had_parse_errors(&self) -> bool {
+        self.parse_errors
+    }
+
+    /// Check if Ini was modified since it was parsed or since the last
+    /// [`mark_clean()`](#method.mark_clean)
+    ///
+    /// Every method changing keys, values, comments or order marks Ini as modified, including
+    /// mutable iteration, even if no value is actually changed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[section]\none = 1");
+    /// assert!(!conf.is_modified());
+    /// conf.insert("section", "two", "2");
+    /// assert!(conf.is_modified());
+    /// conf.mark_clean();
+    /// assert!(!conf.is_modified());
+    /// ```
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Reset the modification flag, e.g. after saving
+    pub fn mark_clean(&mut self) {
+        self.modified = false;
+    }
+
     /// Construct Ini from file
     ///
     /// # Errors
@@ -117,8 +602,93 @@ impl Ini {
         Ok(Ini::from_string(&buffer))
     }
 
+    /// Construct Ini from file using the strict parser
+    ///
+    /// Unlike [`from_file()`](#method.from_file) this function doesn't skip malformed lines.
+    ///
+    /// # Errors
+    /// `IniError::Io` for errors returned by `File::open()` and `BufReader::read_to_string()`,
+    /// `IniError::Parse` for malformed file contents.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, IniError};
+    /// let conf = Ini::try_from_file("./examples/example.ini");
+    /// assert!(conf.is_ok());
+    ///
+    /// let conf = Ini::try_from_file("./examples/missing.ini");
+    /// assert!(matches!(conf, Err(IniError::Io(_))));
+    /// ```
+    pub fn try_from_file<S: AsRef<Path> + ?Sized>(path: &S) -> Result<Ini, IniError> {
+        Ini::from_file_with_options(path, &ParseOptions::default())
+    }
+
+    /// Construct Ini from file using the strict parser with parse options
+    ///
+    /// If [`include_key`](struct.ParseOptions.html#method.include_key) is set, every such key
+    /// is replaced by the contents of the file it names. Relative paths are resolved against the
+    /// directory of the including file. Keys of the included file override previous values and
+    /// are overridden by following ones. Keys before the first section header of the included
+    /// file belong to the section containing the directive, while section headers of the
+    /// included file don't affect the keys following the directive.
+    ///
+    /// # Errors
+    /// Same as [`try_from_file()`](#method.try_from_file) for every read file, and
+    /// `IniError::IncludeCycle` if a file includes itself directly or indirectly.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, ParseOptions};
+    /// let options = ParseOptions::new().include_key("include");
+    /// let conf = Ini::from_file_with_options("./examples/example.ini", &options);
+    /// assert!(conf.is_ok());
+    /// ```
+    pub fn from_file_with_options<S: AsRef<Path> + ?Sized>(
+        path: &S,
+        options: &ParseOptions,
+    ) -> Result<Ini, IniError> {
+        Ini::load_file(path.as_ref(), options, &mut Vec::new())
+    }
+
+    /// Replace contents of Ini in place with contents of file
+    ///
+    /// Output settings like [`line_ending()`](#method.line_ending),
+    /// [`escape_values()`](#method.escape_values) and
+    /// [`section_header()`](#method.section_header) are kept. On error `self` stays untouched.
+    ///
+    /// # Errors
+    /// Errors returned by `File::open()` and `BufReader::read_to_string()`
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("section_one").item("name1", "0");
+    /// conf.reload_from_file("./examples/example.ini").unwrap();
+    /// let name1: Option<i32> = conf.get("section_one", "name1");
+    /// assert_eq!(name1, Some(100));
+    /// ```
+    pub fn reload_from_file<S: AsRef<Path> + ?Sized>(&mut self, path: &S) -> Result<(), io::Error> {
+        let reloaded = Ini::from_file(path)?;
+        *self = Ini {
+            escape_values: self.escape_values,
+            line_ending: self.line_ending,
+            section_header: self.section_header,
+            ..reloaded
+        };
+        Ok(())
+    }
+
     /// Construct Ini from buffer
     ///
+    /// Only the outer brackets of section headers are stripped, inner brackets are part of the
+    /// name: `[[a]]` is section `[a]`.
+    ///
+    /// Values in double quotes with leading or trailing whitespace inside the quotes, like
+    /// `" x "`, are read without the quotes, and values starting and ending with `"""` lose
+    /// two quotes on both sides, as written by [`to_buffer()`](#method.to_buffer). Note that
+    /// this changes the meaning of such values in existing files, which used to be read with
+    /// all their quotes; other quoted values like `"x"` are kept as they are.
+    ///
     /// # Example
     /// ```
     /// # use tini::Ini;
@@ -130,6 +700,86 @@ impl Ini {
         Ini::from_string(&buf.into())
     }
 
+    /// Construct Ini from buffer, passing malformed lines to `on_error` instead of printing them
+    ///
+    /// The handler receives the line number (starting from 1), the line text and the error
+    /// message. Like [`from_buffer()`](#method.from_buffer), this function skips malformed lines.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut errors = Vec::new();
+    /// let conf = Ini::from_buffer_with_handler("[section]\none = 1\nbad", |line, text, _| {
+    ///     errors.push(format!("{}: {}", line, text));
+    /// });
+    /// assert_eq!(errors, ["3: bad"]);
+    /// assert_eq!(conf.to_buffer(), "[section]\none = 1");
+    /// ```
+    pub fn from_buffer_with_handler<S, F>(buf: S, mut on_error: F) -> Ini
+    where
+        S: Into<String>,
+        F: FnMut(usize, &str, &str),
+    {
+        let buffer = buf.into();
+        let lines: Vec<&str> = buffer.lines().collect();
+        let result = Ini::parse(
+            &lines,
+            &ParseOptions::default(),
+            |err| {
+                on_error(err.line(), lines[err.line() - 1], err.message());
+                Ok::<(), ParseError>(())
+            },
+            None,
+        );
+        // errors are never propagated from the handler above
+        result.unwrap_or_default()
+    }
+
+    /// Construct Ini from buffer and count malformed lines
+    ///
+    /// Like [`from_buffer()`](#method.from_buffer), this function skips malformed lines, but
+    /// instead of printing them it returns their number.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let (conf, errors) = Ini::from_buffer_counted("[section]\none = 1\nbad");
+    /// assert_eq!(errors, 1);
+    /// assert_eq!(conf.to_buffer(), "[section]\none = 1");
+    /// ```
+    pub fn from_buffer_counted<S: Into<String>>(buf: S) -> (Ini, usize) {
+        let mut errors = 0;
+        let ini = Ini::from_buffer_with_handler(buf, |_, _, _| errors += 1);
+        (ini, errors)
+    }
+
+    /// Construct Ini from buffer using parse options
+    ///
+    /// Unlike [`from_buffer()`](#method.from_buffer) this function doesn't skip malformed lines.
+    ///
+    /// # Errors
+    /// Returns `ParseError` with the number of the first malformed line or, if
+    /// [`require_section_header`](struct.ParseOptions.html#method.require_section_header) is set,
+    /// of the first key-value pair preceding any section header. Section headers may only be
+    /// followed by whitespace or a comment, e.g. `[section] junk` is malformed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, ParseOptions};
+    /// let options = ParseOptions::new().require_section_header(true);
+    /// let conf = Ini::from_buffer_with_options("[section]\none = 1", &options);
+    /// assert!(conf.is_ok());
+    ///
+    /// let err = Ini::from_buffer_with_options("one = 1\n[section]", &options).unwrap_err();
+    /// assert_eq!(err.line(), 1);
+    /// ```
+    pub fn from_buffer_with_options<S: Into<String>>(
+        buf: S,
+        options: &ParseOptions,
+    ) -> Result<Ini, ParseError> {
+        Ini::from_string_with_options(&buf.into(), options)
+    }
+
     /// Set section name for following [`item()`](#method.item)s. This function doesn't create a
     /// section.
     ///
@@ -144,6 +794,76 @@ impl Ini {
         self
     }
 
+    /// Name of section set by the last [`section()`](#method.section) call, used by following
+    /// [`item()`](#method.item)s
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::new().section("a").item("x", "1").section("b");
+    /// assert_eq!(conf.current_section(), "b");
+    /// ```
+    pub fn current_section(&self) -> &str {
+        &self.last_section_name
+    }
+
+    /// Escape backslashes, ASCII control characters (like `\t` or `\0`) and `;` in values on
+    /// serialization as `\\` and `\xNN`, so arbitrary strings survive a round-trip
+    ///
+    /// Such files should be read with
+    /// [`unescape_values`](struct.ParseOptions.html#method.unescape_values) enabled.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, ParseOptions};
+    /// let conf = Ini::new().section("section").item("tab", "a\tb").escape_values(true);
+    /// assert_eq!(conf.to_buffer(), "[section]\ntab = a\\x09b");
+    ///
+    /// let options = ParseOptions::new().unescape_values(true);
+    /// let conf = Ini::from_buffer_with_options(conf.to_buffer(), &options).unwrap();
+    /// let value: String = conf.get("section", "tab").unwrap();
+    /// assert_eq!(value, "a\tb");
+    /// ```
+    pub fn escape_values(mut self, value: bool) -> Self {
+        self.escape_values = value;
+        self
+    }
+
+    /// Set line break written on serialization, `\n` by default
+    ///
+    /// Line breaks inside values written without
+    /// [`escape_values`](#method.escape_values) are kept as they are.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, LineEnding};
+    /// let conf = Ini::new().section("section").item("one", "1").line_ending(LineEnding::Crlf);
+    /// assert_eq!(conf.to_buffer(), "[section]\r\none = 1");
+    /// ```
+    pub fn line_ending(mut self, value: LineEnding) -> Self {
+        self.line_ending = value;
+        self
+    }
+
+    /// Set function formatting section header lines on serialization, `[name]` by default
+    ///
+    /// The parser ignores whitespace around section names, so headers like `[ name ]` can be
+    /// read back.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::new()
+    ///     .section("section")
+    ///     .item("one", "1")
+    ///     .section_header(|name| format!("[ {} ]", name.to_uppercase()));
+    /// assert_eq!(conf.to_buffer(), "[ SECTION ]\none = 1");
+    /// ```
+    pub fn section_header(mut self, format: fn(&str) -> String) -> Self {
+        self.section_header = format;
+        self
+    }
+
     /// Add key-value pair to last section
     ///
     /// # Example
@@ -156,15 +876,63 @@ impl Ini {
     /// assert_eq!(value, Some(10));
     /// ```
     pub fn item<S: Into<String>>(mut self, name: S, value: S) -> Self {
-        self.data
-            .entry(self.last_section_name.clone())
-            .or_insert_with(Section::new)
-            .insert(name.into(), value.into());
+        let section = self.last_section_name.clone();
+        self.insert(&section, name, value);
         self
     }
 
+    /// Add key-value pair to last section if `cond` is true
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let debug = false;
+    /// let conf = Ini::new().section("test")
+    ///                      .item("value", "10")
+    ///                      .item_if(debug, "verbose", "true");
+    ///
+    /// assert_eq!(conf.to_buffer(), "[test]\nvalue = 10");
+    /// ```
+    pub fn item_if<S: Into<String>>(self, cond: bool, name: S, value: S) -> Self {
+        if cond {
+            self.item(name, value)
+        } else {
+            self
+        }
+    }
+
+    /// Add key-value pair to section through mutable reference, creating the section if needed
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new();
+    /// conf.insert("section", "one", "1");
+    /// let value: Option<u8> = conf.get("section", "one");
+    /// assert_eq!(value, Some(1));
+    /// ```
+    pub fn insert<K, V>(&mut self, section: &str, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.modified = true;
+        let key = key.into();
+        self.vec_formats.remove(&(section.to_owned(), key.clone()));
+        self.data
+            .entry(section.to_owned())
+            .or_default()
+            .insert(key, value.into());
+    }
+
     /// Add key-vector pair to last section separated by sep string
     ///
+    /// The separator is recorded as [`VecFormat`](struct.VecFormat.html) of the key, so
+    /// [`get_vec()`](#method.get_vec) splits the value by it as well.
+    /// Backslashes and occurrences of the separator (without surrounding whitespace) in elements
+    /// are escaped with backslash, so the elements read back unchanged. On reading, `\\` is
+    /// decoded to `\` and other backslashes are kept as they are.
+    ///
     /// # Example
     /// ```
     /// # use tini::Ini;
@@ -173,7 +941,7 @@ impl Ini {
     ///     .item_vec_with_sep("a", &[1, 2, 3, 4], ",")
     ///     .item_vec_with_sep("b", &vec!["a", "b", "c"], "|");
     /// let va: Option<Vec<u8>> = conf.get_vec("default", "a");
-    /// let vb: Vec<String> = conf.get_vec_with_sep("default", "b", "|").unwrap();
+    /// let vb: Vec<String> = conf.get_vec("default", "b").unwrap();
     /// assert_eq!(va, Some(vec![1, 2, 3, 4]));
     /// assert_eq!(vb, ["a", "b", "c"]);
     /// ```
@@ -182,15 +950,23 @@ impl Ini {
         S: Into<String>,
         V: fmt::Display,
     {
+        self.modified = true;
         let vector_data = vector
             .iter()
-            .map(|v| format!("{}", v))
+            .map(|v| escape_element(&v.to_string(), sep).into_owned())
             .collect::<Vec<_>>()
             .join(sep);
+        let name = name.into();
+        self.vec_formats.insert(
+            (self.last_section_name.clone(), name.clone()),
+            VecFormat {
+                sep: sep.to_owned(),
+            },
+        );
         self.data
             .entry(self.last_section_name.clone())
-            .or_insert_with(Section::new)
-            .insert(name.into(), vector_data);
+            .or_default()
+            .insert(name, vector_data);
         self
     }
 
@@ -227,10 +1003,103 @@ impl Ini {
         Ok(())
     }
 
-    /// Write Ini to buffer
+    /// Write Ini to file with given permission bits, e.g. `0o600` for files holding secrets
     ///
-    /// # Example
-    /// ```
+    /// The mode is applied to new and existing files alike. It is only supported on Unix, on
+    /// other platforms this function is the same as [`to_file()`](#method.to_file).
+    ///
+    /// # Errors
+    /// Errors returned by `OpenOptions::open()`, `File::set_permissions()` and
+    /// `BufWriter::write_all()`
+    pub fn to_file_with_mode<S: AsRef<Path> + ?Sized>(
+        &self,
+        path: &S,
+        mode: u32,
+    ) -> Result<(), io::Error> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+        let file = options.open(path)?;
+        // the mode of `open()` only affects new files
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+        #[cfg(not(unix))]
+        let _ = mode;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(self.to_buffer().as_bytes())?;
+        Ok(())
+    }
+
+    /// Write Ini to file atomically
+    ///
+    /// The contents are written to a temporary file next to `path` (its name with the process id,
+    /// a counter and `.tmp` appended, so concurrent writers don't share it), which then replaces
+    /// `path` by `fs::rename()`, so readers never see a partially written file. The rename is
+    /// only atomic within one filesystem, that's why the temporary file is created in the same
+    /// directory. On error the temporary file is removed and `path` stays untouched.
+    ///
+    /// # Errors
+    /// Errors returned by `OpenOptions::open()`, `BufWriter::write_all()`, `File::sync_all()`
+    /// and `fs::rename()`
+    pub fn to_file_atomic<S: AsRef<Path> + ?Sized>(&self, path: &S) -> Result<(), io::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = path.as_ref();
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        name.push(format!(".{}.{}.tmp", process::id(), count));
+        let tmp = path.with_file_name(name);
+        let file = OpenOptions::new().write(true).create_new(true).open(&tmp);
+        let result = file.and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            writer.write_all(self.to_buffer().as_bytes())?;
+            writer.into_inner()?.sync_all()?;
+            fs::rename(&tmp, path)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    /// Append a single section to the end of file, without rewriting its contents
+    ///
+    /// The file is created if it does not exist. If it is not empty, the section is separated
+    /// from the existing contents by a blank line.
+    ///
+    /// # Errors
+    /// `io::ErrorKind::NotFound` if the section does not exist, and errors returned by
+    /// `OpenOptions::open()` and `File::write_all()`
+    pub fn append_section_to_file<S: AsRef<Path> + ?Sized>(
+        &self,
+        path: &S,
+        section: &str,
+    ) -> Result<(), io::Error> {
+        if !self.data.contains_key(section) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("section not found: {}", section),
+            ));
+        }
+        let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+        let mut buffer = String::new();
+        if file.metadata()?.len() > 0 {
+            buffer.push_str(self.line_ending.as_str());
+            buffer.push_str(self.line_ending.as_str());
+        }
+        buffer.push_str(&self.to_buffer_filtered(|name| name == section));
+        file.write_all(buffer.as_bytes())
+    }
+
+    /// Write Ini to buffer
+    ///
+    /// Values with leading or trailing whitespace are written in double quotes,
+    /// so they are read back unchanged. Values which are already quoted that way, or which start
+    /// and end with `"""`, get two more quotes on both sides, e.g. `" x "` is written as
+    /// `""" x """`.
+    ///
+    /// # Example
+    /// ```
     /// # use tini::Ini;
     /// let conf = Ini::from_buffer("[section]\none = 1");
     /// // you may use `conf.to_buffer()`
@@ -244,10 +1113,230 @@ impl Ini {
         format!("{}", self)
     }
 
+    /// Length in bytes of the buffer [`to_buffer()`](#method.to_buffer) would produce, computed
+    /// without building it
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\none = 1");
+    /// let mut buffer = String::with_capacity(conf.serialized_len());
+    /// buffer.push_str(&conf.to_buffer());
+    /// assert_eq!(conf.serialized_len(), buffer.len());
+    /// ```
+    pub fn serialized_len(&self) -> usize {
+        let newline = self.line_ending.as_str().len();
+        let mut len = 0;
+        for (section, iter) in self.iter() {
+            if let Some(comment) = self.section_comment(section) {
+                len += comment
+                    .lines()
+                    .map(|line| line.len() + 2 + newline)
+                    .sum::<usize>();
+            }
+            len += (self.section_header)(section).len() + newline;
+            for (key, value) in iter {
+                if let Some(comment) = self.comment_for(section, key) {
+                    len += comment
+                        .lines()
+                        .map(|line| line.len() + 2 + newline)
+                        .sum::<usize>();
+                }
+                len += escape_key(key).len() + 3;
+                len += if self.escape_values {
+                    escaped_len(value)
+                } else {
+                    value.len()
+                };
+                len += 2 * quote_level(value, self.escape_values);
+                if let Some(comment) = self.inline_comment_for(section, key) {
+                    len += comment.len() + 3;
+                }
+                len += newline;
+            }
+            len += newline;
+        }
+        // the last two line breaks are not written
+        len.saturating_sub(2 * newline)
+    }
+
+    /// Write sections passing the predicate to buffer, preserving order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[server.a]\nport = 80\n[db]\nport = 5432\n[server.b]\nport = 81");
+    /// let servers = conf.to_buffer_filtered(|name| name.starts_with("server."));
+    /// assert_eq!(servers, "[server.a]\nport = 80\n\n[server.b]\nport = 81");
+    /// ```
+    pub fn to_buffer_filtered<F: Fn(&str) -> bool>(&self, filter: F) -> String {
+        self.serialize(filter)
+    }
+
+    /// Write Ini to JSON object of section objects, preserving order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\none = 1\ntwo = \"2\"");
+    /// assert_eq!(conf.to_json_string(), r#"{"section": {"one": "1", "two": "\"2\""}}"#);
+    /// ```
+    pub fn to_json_string(&self) -> String {
+        let sections: Vec<String> = self
+            .iter()
+            .map(|(section, iter)| {
+                let items: Vec<String> = iter
+                    .map(|(key, value)| format!("{}: {}", json_string(key), json_string(value)))
+                    .collect();
+                format!("{}: {{{}}}", json_string(section), items.join(", "))
+            })
+            .collect();
+        format!("{{{}}}", sections.join(", "))
+    }
+
+    /// Write Ini to TOML-like string, preserving order
+    ///
+    /// Best effort: values looking like TOML booleans, integers or floats are written bare, all
+    /// others as quoted strings. Keys and section names are quoted unless they are bare TOML keys,
+    /// but a dot in a section name still makes a nested table. Lists, dates and nested tables are
+    /// not detected.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nname = tini\nport = 80\nratio = 0.5\ndebug = true");
+    /// assert_eq!(
+    ///     conf.to_toml_string(),
+    ///     "[section]\nname = \"tini\"\nport = 80\nratio = 0.5\ndebug = true"
+    /// );
+    /// ```
+    pub fn to_toml_string(&self) -> String {
+        let sections: Vec<String> = self
+            .iter()
+            .map(|(section, iter)| {
+                let mut lines = vec![format!("[{}]", toml_key(section, true))];
+                lines.extend(iter.map(|(key, value)| {
+                    if value == "true" || value == "false" || is_toml_number(value) {
+                        format!("{} = {}", toml_key(key, false), value)
+                    } else {
+                        format!("{} = {}", toml_key(key, false), json_string(value))
+                    }
+                }));
+                lines.join("\n")
+            })
+            .collect();
+        sections.join("\n\n")
+    }
+
+    /// Check if Ini is read back unchanged after writing it
+    ///
+    /// Serializes to a buffer and parses it again, with
+    /// [`unescape_values`](struct.ParseOptions.html#method.unescape_values) if
+    /// [`escape_values`](#method.escape_values) is set, then compares all key-value pairs
+    /// regardless of order. Comments and empty sections are not compared. Useful before writing
+    /// configs holding tricky values, like line breaks.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::new().section("a").item("text", "line 1\nline 2");
+    /// assert!(!conf.check_roundtrip());
+    /// assert!(conf.escape_values(true).check_roundtrip());
+    /// ```
+    pub fn check_roundtrip(&self) -> bool {
+        let options = ParseOptions::new().unescape_values(self.escape_values);
+        let buffer = self.to_buffer();
+        let parsed = Ini::parse(buffer.lines(), &options, |_| Err(()), None);
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(()) => return false,
+        };
+        let pairs = |ini: &Ini| -> HashSet<(String, String, String)> {
+            ini.iter()
+                .flat_map(|(section, iter)| {
+                    iter.map(move |(key, value)| (section.clone(), key.clone(), value.clone()))
+                })
+                .collect()
+        };
+        pairs(self) == pairs(&parsed)
+    }
+
+    fn serialize<F: Fn(&str) -> bool>(&self, filter: F) -> String {
+        let newline = self.line_ending.as_str();
+        let mut buffer = String::new();
+        for (section, iter) in self.iter().filter(|(section, _)| filter(section)) {
+            if let Some(comment) = self.section_comment(section) {
+                for line in comment.lines() {
+                    buffer.push_str(&format!("; {}{}", line, newline));
+                }
+            }
+            buffer.push_str(&(self.section_header)(section));
+            buffer.push_str(newline);
+            for (key, value) in iter {
+                if let Some(comment) = self.comment_for(section, key) {
+                    for line in comment.lines() {
+                        buffer.push_str(&format!("; {}{}", line, newline));
+                    }
+                }
+                let escaped_key = escape_key(key);
+                let value = if self.escape_values {
+                    escape(value)
+                } else {
+                    value.clone()
+                };
+                // whitespace around value and quotes stripped on parsing are kept in quotes
+                let quotes = "\"".repeat(quote_level(&value, false));
+                buffer.push_str(&format!("{} = {}{}{}", escaped_key, quotes, value, quotes));
+                if let Some(comment) = self.inline_comment_for(section, key) {
+                    buffer.push_str(&format!(" ; {}", comment));
+                }
+                buffer.push_str(newline);
+            }
+            // blank line between sections
+            buffer.push_str(newline);
+        }
+        // remove last two line breaks
+        buffer.truncate(buffer.len().saturating_sub(2 * newline.len()));
+        buffer
+    }
+
     fn get_raw(&self, section: &str, key: &str) -> Option<&String> {
         self.data.get(section).and_then(|x| x.get(key))
     }
 
+    /// Get raw value of key in section as a string slice
+    ///
+    /// Unlike [`get()`](#method.get), this function never allocates: the result points into the
+    /// stored value.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nname = value");
+    /// assert_eq!(conf.get_ref("section", "name"), Some("value"));
+    /// ```
+    pub fn get_ref(&self, section: &str, key: &str) -> Option<&str> {
+        self.get_raw(section, key).map(String::as_str)
+    }
+
+    /// Get raw value of key in section without allocation
+    ///
+    /// The result always borrows the stored value, `Cow` only lets callers turn it into an owned
+    /// string when needed.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::borrow::Cow;
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nname = value");
+    /// let value = conf.get_cow("section", "name").unwrap();
+    /// assert!(matches!(value, Cow::Borrowed("value")));
+    /// ```
+    pub fn get_cow(&self, section: &str, key: &str) -> Option<Cow<'_, str>> {
+        self.get_raw(section, key)
+            .map(|x| Cow::Borrowed(x.as_str()))
+    }
+
     /// Get scalar value of key in section
     ///
     /// # Example
@@ -261,306 +1350,3669 @@ impl Ini {
         self.get_raw(section, key).and_then(|x| x.parse().ok())
     }
 
-    /// Get vector value of key in section
+    /// Get scalar value of key in section, or `T::default()` if the key is missing or its value
+    /// can not be parsed
     ///
-    /// The function returns `None` if one of the elements can not be parsed.
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\none = 1\ntwo = abc");
+    /// assert_eq!(conf.get_or_default::<u8>("section", "one"), 1);
+    /// assert_eq!(conf.get_or_default::<u8>("section", "two"), 0);
+    /// ```
+    pub fn get_or_default<T: FromStr + Default>(&self, section: &str, key: &str) -> T {
+        self.get(section, key).unwrap_or_default()
+    }
+
+    /// Get value of key in section mapped through `(name, value)` pairs
+    ///
+    /// Useful for enums without a `FromStr` implementation. Names are compared ignoring ASCII
+    /// case, but an exact match takes precedence. Evaluates to `None` if the key is missing or
+    /// its value matches no name.
     ///
     /// # Example
     /// ```
     /// # use tini::Ini;
-    /// let conf = Ini::from_buffer("[section]\nlist = 1, 2, 3, 4");
-    /// let value: Option<Vec<u8>> = conf.get_vec("section", "list");
-    /// assert_eq!(value, Some(vec![1, 2, 3, 4]));
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Level { Debug, Info }
+    /// let conf = Ini::from_buffer("[log]\nlevel = Debug");
+    /// let levels = [("debug", Level::Debug), ("info", Level::Info)];
+    /// assert_eq!(conf.get_mapped("log", "level", &levels), Some(Level::Debug));
     /// ```
-    pub fn get_vec<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
-    where
-        T: FromStr,
-    {
-        self.get_vec_with_sep(section, key, ",")
+    pub fn get_mapped<T: Clone>(&self, section: &str, key: &str, map: &[(&str, T)]) -> Option<T> {
+        let raw = self.get_raw(section, key)?;
+        map.iter()
+            .find(|(name, _)| name == raw)
+            .or_else(|| map.iter().find(|(name, _)| name.eq_ignore_ascii_case(raw)))
+            .map(|(_, value)| value.clone())
     }
 
-    /// Get vector value of key in section separeted by sep string
+    /// Get scalar value of key in section along with its raw value
     ///
-    /// The function returns `None` if one of the elements can not be parsed.
+    /// Useful for diagnostics: the raw value is `Some` whenever the key exists, even if it can
+    /// not be parsed.
     ///
     /// # Example
     /// ```
     /// # use tini::Ini;
-    /// let conf = Ini::from_buffer("[section]\nlist = 1|2|3|4");
-    /// let value: Option<Vec<u8>> = conf.get_vec_with_sep("section", "list", "|");
-    /// assert_eq!(value, Some(vec![1, 2, 3, 4]));
+    /// let conf = Ini::from_buffer("[server]\nport = abc");
+    /// let (port, raw) = conf.get_verbose::<u16>("server", "port");
+    /// assert_eq!(port, None);
+    /// assert_eq!(raw, Some("abc"));
     /// ```
-    pub fn get_vec_with_sep<T>(&self, section: &str, key: &str, sep: &str) -> Option<Vec<T>>
-    where
-        T: FromStr,
-    {
-        self.get_raw(section, key).and_then(|x| {
-            x.split(sep)
-                .map(|s| s.trim().parse())
-                .collect::<Result<Vec<T>, _>>()
-                .ok()
-        })
+    pub fn get_verbose<T: FromStr>(&self, section: &str, key: &str) -> (Option<T>, Option<&str>) {
+        let raw = self.get_raw(section, key).map(String::as_str);
+        (raw.and_then(|x| x.parse().ok()), raw)
     }
 
-    /// Iterate over a section by a name
+    /// Get scalar value of key from the first of `sections` where it can be parsed
+    ///
+    /// Useful for layered configs, where a key of a specific section overrides the default one.
     ///
     /// # Example
     /// ```
     /// # use tini::Ini;
-    /// let conf = Ini::from_buffer(["[search]",
-    ///                         "g = google.com",
-    ///                         "dd = duckduckgo.com"].join("\n"));
-    /// let search = conf.iter_section("search").unwrap();
-    /// for (k, v) in search {
-    ///   println!("key: {} value: {}", k, v);
-    /// }
+    /// let conf = Ini::from_buffer("[default]\nport = 80\nhost = localhost\n[profile.dev]\nport = 8080");
+    /// let port: Option<u16> = conf.get_fallback(&["profile.dev", "default"], "port");
+    /// let host: Option<String> = conf.get_fallback(&["profile.dev", "default"], "host");
+    /// assert_eq!(port, Some(8080));
+    /// assert_eq!(host, Some("localhost".to_owned()));
     /// ```
-    pub fn iter_section(&self, section: &str) -> Option<SectionIter> {
-        self.data.get(section).map(|value| value.iter())
+    pub fn get_fallback<T: FromStr>(&self, sections: &[&str], key: &str) -> Option<T> {
+        sections.iter().find_map(|section| self.get(section, key))
     }
 
-    /// Iterate over all sections, yielding pairs of section name and iterator
-    /// over the section elements. The concrete iterator element type is
-    /// `(&'a String, ordered_hashmap::Iter<'a, String, String>)`.
+    /// Get IP address value of key in section
+    ///
+    /// Both IPv4 (`127.0.0.1`) and IPv6 (`::1`) addresses are accepted.
     ///
     /// # Example
     /// ```
+    /// # use std::net::{IpAddr, Ipv6Addr};
     /// # use tini::Ini;
-    /// let conf = Ini::new().section("foo")
-    ///                      .item("item", "value")
-    ///                      .item("other", "something")
-    ///                      .section("bar")
-    ///                      .item("one", "1");
-    /// for (section, iter) in conf.iter() {
-    ///   for (key, val) in iter {
-    ///     println!("section: {} key: {} val: {}", section, key, val);
-    ///   }
-    /// }
-    pub fn iter(&self) -> IniIter {
-        IniIter {
-            iter: self.data.iter(),
+    /// let conf = Ini::from_buffer("[server]\nhost = ::1");
+    /// assert_eq!(conf.get_ip_addr("server", "host"), Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    /// ```
+    pub fn get_ip_addr(&self, section: &str, key: &str) -> Option<IpAddr> {
+        self.get(section, key)
+    }
+
+    /// Get socket address value of key in section
+    ///
+    /// The value is an IP address with port, IPv6 addresses are written in brackets:
+    /// `127.0.0.1:8080` or `[::1]:8080`. Brackets in values don't interfere with section headers,
+    /// which only start at the beginning of a line.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::net::SocketAddr;
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[server]\nlisten = [::1]:8080");
+    /// let addr: SocketAddr = "[::1]:8080".parse().unwrap();
+    /// assert_eq!(conf.get_socket_addr("server", "listen"), Some(addr));
+    /// ```
+    pub fn get_socket_addr(&self, section: &str, key: &str) -> Option<SocketAddr> {
+        self.get(section, key)
+    }
+
+    /// Get integer value of key in section written in decimal or with a radix prefix
+    ///
+    /// Accepted prefixes are `0x` (hexadecimal), `0o` (octal) and `0b` (binary), in any case,
+    /// after an optional sign: `0xFF`, `0o777`, `-0b1010`. Values without prefix are decimal.
+    /// Plain [`get()`](#method.get) uses `FromStr`, which only accepts decimal integers.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nmask = 0xFF\nmode = 0o755");
+    /// assert_eq!(conf.get_int_radix::<u8>("section", "mask"), Some(255));
+    /// assert_eq!(conf.get_int_radix::<u32>("section", "mode"), Some(0o755));
+    /// ```
+    pub fn get_int_radix<T: FromStrRadix>(&self, section: &str, key: &str) -> Option<T> {
+        let value = self.get_raw(section, key)?.trim();
+        let (sign, unsigned) = match value.strip_prefix(['+', '-']) {
+            Some(rest) => (&value[..1], rest),
+            None => ("", value),
+        };
+        let prefix = unsigned.get(..2).map(str::to_ascii_lowercase);
+        let (radix, digits) = match prefix.as_deref() {
+            Some("0x") => (16, &unsigned[2..]),
+            Some("0o") => (8, &unsigned[2..]),
+            Some("0b") => (2, &unsigned[2..]),
+            _ => (10, unsigned),
+        };
+        // a sign after the prefix is malformed
+        if digits.starts_with(['+', '-']) {
+            return None;
         }
+        T::from_str_radix(&format!("{}{}", sign, digits), radix).ok()
     }
 
-    /// Iterate over all sections, yielding pairs of section name and mutable
-    /// iterator over the section elements. The concrete iterator element type is
-    /// `(&'a String, ordered_hashmap::IterMut<'a, String, String>)`.
+    /// Get scalar value of key in section with references to other keys resolved
+    ///
+    /// References are replaced by the (resolved) values of the keys they name:
+    ///
+    /// * `%(key)s` and `${key}` refer to a key in the same section;
+    /// * `${section:key}` refers to a key in another section;
+    /// * `%%` and `$$` stand for literal `%` and `$`.
+    ///
+    /// Other `%` and `$` characters are kept as is. Plain [`get()`](#method.get) never resolves
+    /// references. Returns `Ok(None)` if the key is missing or the resolved value can not be
+    /// parsed. Resolved values are limited to 1 MiB.
+    ///
+    /// # Errors
+    /// `InterpolationError::Missing` if a referenced key doesn't exist,
+    /// `InterpolationError::Cycle` if a value refers to itself directly or indirectly,
+    /// `InterpolationError::TooLong` if a resolved value exceeds the limit.
     ///
     /// # Example
     /// ```
     /// # use tini::Ini;
-    /// let mut conf = Ini::new().section("foo")
-    ///                          .item("item", "value")
-    ///                          .item("other", "something")
-    ///                          .section("bar")
-    ///                          .item("one", "1");
-    /// for (section, iter_mut) in conf.iter_mut() {
-    ///   for (key, val) in iter_mut {
-    ///     *val = String::from("replaced");
-    ///   }
-    /// }
-    pub fn iter_mut(&mut self) -> IniIterMut {
-        IniIterMut {
-            iter: self.data.iter_mut(),
+    /// let conf = Ini::from_buffer("[paths]\nbase = /opt\ndata = %(base)s/data\n[app]\nlog = ${paths:data}/log");
+    /// let log: Option<String> = conf.get_interpolated("app", "log").unwrap();
+    /// assert_eq!(log, Some("/opt/data/log".to_owned()));
+    /// ```
+    pub fn get_interpolated<T: FromStr>(
+        &self,
+        section: &str,
+        key: &str,
+    ) -> Result<Option<T>, InterpolationError> {
+        if self.get_raw(section, key).is_none() {
+            return Ok(None);
         }
+        let value = self.resolve(section, key, &mut Vec::new(), &mut HashMap::new())?;
+        Ok(value.parse().ok())
     }
-}
 
-impl fmt::Display for Ini {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut buffer = String::new();
-        for (section, iter) in self.iter() {
-            buffer.push_str(&format!("[{}]\n", section));
-            for (key, value) in iter {
-                buffer.push_str(&format!("{} = {}\n", key, value));
+    /// Resolve references in value of key in section; `chain` holds keys being resolved, to
+    /// detect cycles, `resolved` caches values of keys already resolved
+    fn resolve(
+        &self,
+        section: &str,
+        key: &str,
+        chain: &mut Vec<(String, String)>,
+        resolved: &mut HashMap<(String, String), String>,
+    ) -> Result<String, InterpolationError> {
+        const MAX_LEN: usize = 1 << 20;
+        let reference = (section.to_owned(), key.to_owned());
+        if let Some(value) = resolved.get(&reference) {
+            return Ok(value.clone());
+        }
+        let value = match self.get_raw(section, key) {
+            Some(value) => value,
+            None => return Err(InterpolationError::Missing(reference.0, reference.1)),
+        };
+        if chain.contains(&reference) {
+            return Err(InterpolationError::Cycle(reference.0, reference.1));
+        }
+        chain.push(reference.clone());
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value.as_str();
+        while let Some(pos) = rest.find(['%', '$']) {
+            result.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            let target = if let Some(tail) = rest.strip_prefix("%(") {
+                tail.find(")s").map(|end| (section, &tail[..end], end + 4))
+            } else if let Some(tail) = rest.strip_prefix("${") {
+                tail.find('}').map(|end| match tail[..end].split_once(':') {
+                    Some((section, key)) => (section, key, end + 3),
+                    None => (section, &tail[..end], end + 3),
+                })
+            } else {
+                None
+            };
+            match target {
+                Some((section, key, len)) => {
+                    result.push_str(&self.resolve(section, key, chain, resolved)?);
+                    rest = &rest[len..];
+                    if result.len() > MAX_LEN {
+                        return Err(InterpolationError::TooLong(reference.0, reference.1));
+                    }
+                }
+                None if rest.starts_with("%%") || rest.starts_with("$$") => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[2..];
+                }
+                None => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
             }
-            // blank line between sections
-            buffer.push_str("\n");
         }
-        // remove last two '\n'
-        buffer.pop();
-        buffer.pop();
-        write!(f, "{}", buffer)
+        result.push_str(rest);
+        if result.len() > MAX_LEN {
+            return Err(InterpolationError::TooLong(reference.0, reference.1));
+        }
+        chain.pop();
+        resolved.insert(reference, result.clone());
+        Ok(result)
     }
-}
 
-impl Default for Ini {
-    fn default() -> Self {
-        Self::new()
+    /// Get duration value of key in section
+    ///
+    /// The value is a non-negative number, possibly fractional, followed by an optional unit:
+    /// `ms` (milliseconds), `s` (seconds), `m` (minutes), `h` (hours) or `d` (days). A bare number
+    /// means seconds. Whitespace between the number and the unit is allowed. The function returns
+    /// `None` for unknown units, malformed numbers and durations exceeding `u64::MAX` seconds.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\ntimeout = 5m\ndelay = 250ms");
+    /// assert_eq!(conf.get_duration("section", "timeout"), Some(Duration::from_secs(300)));
+    /// assert_eq!(conf.get_duration("section", "delay"), Some(Duration::from_millis(250)));
+    /// ```
+    pub fn get_duration(&self, section: &str, key: &str) -> Option<Duration> {
+        let value = self.get_raw(section, key)?.trim();
+        let split = value
+            .find(|c: char| c.is_alphabetic())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: f64 = number.trim_end().parse().ok()?;
+        if !number.is_finite() || number < 0.0 {
+            return None;
+        }
+        let seconds = match unit {
+            "ms" => number / 1000.0,
+            "" | "s" => number,
+            "m" => number * 60.0,
+            "h" => number * 3600.0,
+            "d" => number * 86400.0,
+            _ => return None,
+        };
+        // `from_secs_f64()` panics on values not fitting in `Duration`
+        if seconds >= u64::MAX as f64 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(seconds))
     }
-}
-
-#[doc(hidden)]
-pub struct IniIter<'a> {
-    iter: ordered_hashmap::Iter<'a, String, Section>,
-}
-
-impl<'a> Iterator for IniIter<'a> {
-    type Item = (&'a String, SectionIter<'a>);
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next()
-            .map(|(string, section)| (string, section.iter()))
+    /// Get byte size value of key in section
+    ///
+    /// The value is a non-negative integer followed by an optional unit. SI units `K`, `M`, `G`
+    /// and `T` (optionally followed by `B`) are powers of 1000, binary units `KiB`, `MiB`, `GiB`
+    /// and `TiB` are powers of 1024. A bare number or `B` means bytes. Whitespace between the
+    /// number and the unit is allowed, units are case-sensitive. The function returns `None`
+    /// for unknown units, malformed numbers and sizes exceeding `u64`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\ncache = 256M\nheap = 4GiB");
+    /// assert_eq!(conf.get_bytes_size("section", "cache"), Some(256_000_000));
+    /// assert_eq!(conf.get_bytes_size("section", "heap"), Some(4 << 30));
+    /// ```
+    pub fn get_bytes_size(&self, section: &str, key: &str) -> Option<u64> {
+        let value = self.get_raw(section, key)?.trim();
+        let split = value
+            .find(|c: char| c.is_alphabetic())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: u64 = number.trim_end().parse().ok()?;
+        let multiplier: u64 = match unit {
+            "" | "B" => 1,
+            "K" | "KB" => 1000,
+            "M" | "MB" => 1_000_000,
+            "G" | "GB" => 1_000_000_000,
+            "T" | "TB" => 1_000_000_000_000,
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            "TiB" => 1 << 40,
+            _ => return None,
+        };
+        number.checked_mul(multiplier)
     }
-}
-
-#[doc(hidden)]
-pub struct IniIterMut<'a> {
-    iter: ordered_hashmap::IterMut<'a, String, Section>,
-}
-
-impl<'a> Iterator for IniIterMut<'a> {
-    type Item = (&'a String, SectionIterMut<'a>);
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next()
-            .map(|(string, section)| (string, section.iter_mut()))
+    /// Get bytes value of key in section
+    ///
+    /// Two formats are accepted: if the value contains a comma, it is a comma-separated list of
+    /// bytes written as one or two hex digits (`DE, AD, BE, EF`), otherwise it is a continuous
+    /// string of hex digit pairs (`deadbeef`). Hex digits are case-insensitive. The function
+    /// returns `None` for malformed values, e.g. a hex string of odd length.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = DE, AD, BE, EF\nhex = deadbeef");
+    /// assert_eq!(conf.get_bytes("section", "list"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+    /// assert_eq!(conf.get_bytes("section", "hex"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+    /// ```
+    pub fn get_bytes(&self, section: &str, key: &str) -> Option<Vec<u8>> {
+        fn parse_byte(hex: &str) -> Option<u8> {
+            if hex.is_empty() || hex.len() > 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            u8::from_str_radix(hex, 16).ok()
+        }
+        let value = self.get_raw(section, key)?;
+        if value.contains(',') {
+            value.split(',').map(|x| parse_byte(x.trim())).collect()
+        } else if value.len() % 2 == 0 && value.is_ascii() {
+            (0..value.len())
+                .step_by(2)
+                .map(|i| parse_byte(&value[i..i + 2]))
+                .collect()
+        } else {
+            None
+        }
     }
-}
 
-#[cfg(test)]
+    /// Get range value of key in section expanded to a vector
+    ///
+    /// The value is either a single number or two numbers joined by `-`, like `8000-8005`. Both
+    /// bounds are inclusive; if the first bound is greater, the range is descending. Negative
+    /// bounds are allowed, e.g. `-2--5`. The function returns `None` for malformed values and
+    /// for ranges of more than 65536 elements, to bound memory use.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[server]\nports = 8000-8003\nlevels = 3-1");
+    /// assert_eq!(conf.get_range("server", "ports"), Some(vec![8000, 8001, 8002, 8003]));
+    /// assert_eq!(conf.get_range("server", "levels"), Some(vec![3, 2, 1]));
+    /// ```
+    pub fn get_range(&self, section: &str, key: &str) -> Option<Vec<i64>> {
+        let value = self.get_raw(section, key)?.trim();
+        // skip the sign of the first bound
+        let (start, end) = match value.get(1..)?.find('-') {
+            Some(pos) => (&value[..pos + 1], &value[pos + 2..]),
+            None => (value, value),
+        };
+        let start: i64 = start.trim().parse().ok()?;
+        let end: i64 = end.trim().parse().ok()?;
+        const MAX_LEN: i128 = 65536;
+        if (i128::from(start) - i128::from(end)).abs() >= MAX_LEN {
+            return None;
+        }
+        if start <= end {
+            Some((start..=end).collect())
+        } else {
+            Some((end..=start).rev().collect())
+        }
+    }
+
+    /// Get vector value of key in section
+    ///
+    /// Elements are separated by comma or, if the key was added by
+    /// [`item_vec_with_sep()`](#method.item_vec_with_sep), by the recorded separator.
+    /// The function returns `None` if one of the elements can not be parsed.
+    ///
+    /// Backslashes in list values are escapes, as written by [`item_vec()`](#method.item_vec):
+    /// `\,` is a comma inside an element and `\\` a single backslash, other backslashes are kept.
+    /// Note that this changes how existing values with backslashes are read, e.g. Windows paths:
+    /// `C:\dir\\,D:\x` yields `C:\dir\` and `D:\x`, and `C:\dir\,D:\x` is a single element.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, 2, 3, 4");
+    /// let value: Option<Vec<u8>> = conf.get_vec("section", "list");
+    /// assert_eq!(value, Some(vec![1, 2, 3, 4]));
+    /// ```
+    pub fn get_vec<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        let sep = self.vec_separator(section, key);
+        self.get_vec_with_sep(section, key, sep)
+    }
+
+    /// Get vector value of key in section, skipping empty elements
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec), but elements which are empty
+    /// after trimming are dropped before parsing, wherever they occur: `1,,3,` yields `[1, 3]`.
+    /// The function returns `None` if one of the remaining elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, , 3,");
+    /// let value: Option<Vec<u8>> = conf.get_vec_skip_empty("section", "list");
+    /// assert_eq!(value, Some(vec![1, 3]));
+    /// ```
+    pub fn get_vec_skip_empty<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        let sep = self.vec_separator(section, key);
+        self.vec_elements(section, key, sep).and_then(|elements| {
+            elements
+                .filter(|element| !element.is_empty())
+                .map(|element| element.parse())
+                .collect::<Result<Vec<T>, _>>()
+                .ok()
+        })
+    }
+
+    /// Get vector value of key in section with exactly `n` elements
+    ///
+    /// Like [`get_vec()`](#method.get_vec), but returns `None` if the number of elements differs
+    /// from `n`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nrgb = 255, 128, 0");
+    /// let value: Option<Vec<u8>> = conf.get_vec_exact("section", "rgb", 3);
+    /// assert_eq!(value, Some(vec![255, 128, 0]));
+    /// let value: Option<Vec<u8>> = conf.get_vec_exact("section", "rgb", 4);
+    /// assert_eq!(value, None);
+    /// ```
+    pub fn get_vec_exact<T>(&self, section: &str, key: &str, n: usize) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        self.get_vec(section, key)
+            .filter(|vec: &Vec<T>| vec.len() == n)
+    }
+
+    /// Get vector value of key in section, replacing elements which can not be parsed by `default`
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec). The function returns `None`
+    /// only if the key does not exist.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, x, 3");
+    /// let value: Option<Vec<u8>> = conf.get_vec_or_default("section", "list", 0);
+    /// assert_eq!(value, Some(vec![1, 0, 3]));
+    /// ```
+    pub fn get_vec_or_default<T>(&self, section: &str, key: &str, default: T) -> Option<Vec<T>>
+    where
+        T: FromStr + Clone,
+    {
+        let sep = self.vec_separator(section, key);
+        self.vec_elements(section, key, sep).map(|elements| {
+            elements
+                .map(|element| element.parse().unwrap_or_else(|_| default.clone()))
+                .collect()
+        })
+    }
+
+    /// Get vector value of key in section, split at runs of whitespace
+    ///
+    /// Leading, trailing and repeated whitespace produce no empty elements, so an empty value
+    /// yields an empty vector. The function returns `None` if one of the elements can not be
+    /// parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[build]\nflags = -O2  -g\t-Wall");
+    /// let value: Option<Vec<String>> = conf.get_vec_ws("build", "flags");
+    /// assert_eq!(value.unwrap(), ["-O2", "-g", "-Wall"]);
+    /// ```
+    pub fn get_vec_ws<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        self.get_raw(section, key).and_then(|x| {
+            x.split_whitespace()
+                .map(|element| element.parse())
+                .collect::<Result<Vec<T>, _>>()
+                .ok()
+        })
+    }
+
+    /// Get vector value from indexed keys `base_key.0`, `base_key.1`, ... in section
+    ///
+    /// Elements are ordered by numeric index. Gaps in the indices are skipped, so `item.0` and
+    /// `item.2` yield two elements. Keys with non-numeric suffixes are ignored. The function
+    /// returns `None` if no indexed key exists or one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nserver.1 = b\nserver.0 = a");
+    /// let value: Vec<String> = conf.get_indexed_vec("section", "server").unwrap();
+    /// assert_eq!(value, ["a", "b"]);
+    /// ```
+    pub fn get_indexed_vec<T>(&self, section: &str, base_key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        let mut elements: Vec<(usize, &String)> = self
+            .iter_section(section)?
+            .filter_map(|(key, value)| {
+                let index = key.strip_prefix(base_key)?.strip_prefix('.')?;
+                if !index.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                Some((index.parse().ok()?, value))
+            })
+            .collect();
+        if elements.is_empty() {
+            return None;
+        }
+        elements.sort_by_key(|(index, _)| *index);
+        elements
+            .into_iter()
+            .map(|(_, value)| value.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .ok()
+    }
+
+    /// Get vector of parse results of every element of key in section
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec), but elements which can not be
+    /// parsed are reported individually instead of failing the whole vector.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, x, 3");
+    /// let value = conf.parse_vec::<u8>("section", "list").unwrap();
+    /// assert_eq!(value[0], Ok(1));
+    /// assert!(value[1].is_err());
+    /// assert_eq!(value[2], Ok(3));
+    /// ```
+    pub fn parse_vec<T>(&self, section: &str, key: &str) -> Option<Vec<Result<T, T::Err>>>
+    where
+        T: FromStr,
+    {
+        let sep = self.vec_separator(section, key);
+        self.vec_elements(section, key, sep)
+            .map(|elements| elements.map(|element| element.parse()).collect())
+    }
+
+    /// Get vector value of key in section stored inline for up to `N` elements
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec). Short lists avoid heap
+    /// allocation. The function returns `None` if one of the elements can not be parsed.
+    ///
+    /// Available with the `smallvec` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nrgb = 255, 128, 0");
+    /// let rgb = conf.get_smallvec::<u8, 3>("section", "rgb").unwrap();
+    /// assert!(!rgb.spilled());
+    /// assert_eq!(rgb.as_slice(), [255, 128, 0]);
+    /// ```
+    #[cfg(feature = "smallvec")]
+    pub fn get_smallvec<T, const N: usize>(
+        &self,
+        section: &str,
+        key: &str,
+    ) -> Option<smallvec::SmallVec<[T; N]>>
+    where
+        T: FromStr,
+    {
+        let sep = self.vec_separator(section, key);
+        self.vec_elements(section, key, sep).and_then(|elements| {
+            elements
+                .map(|element| element.parse())
+                .collect::<Result<_, _>>()
+                .ok()
+        })
+    }
+
+    /// Get vector value of key in section with position of every element
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec). The function returns `None` if
+    /// one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, 2, 3");
+    /// let value: Vec<(usize, u8)> = conf.get_enumerated("section", "list").unwrap();
+    /// assert_eq!(value, [(0, 1), (1, 2), (2, 3)]);
+    /// ```
+    pub fn get_enumerated<T>(&self, section: &str, key: &str) -> Option<Vec<(usize, T)>>
+    where
+        T: FromStr,
+    {
+        self.get_vec(section, key)
+            .map(|vector| vector.into_iter().enumerate().collect())
+    }
+
+    /// Get set of values of key in section
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec), duplicates are collapsed.
+    /// The function returns `None` if one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, 2, 2, 3");
+    /// let value: HashSet<u8> = conf.get_set("section", "list").unwrap();
+    /// assert_eq!(value, [1, 2, 3].iter().cloned().collect());
+    /// ```
+    pub fn get_set<T>(&self, section: &str, key: &str) -> Option<HashSet<T>>
+    where
+        T: FromStr + Eq + Hash,
+    {
+        self.get_vec(section, key)
+            .map(|vector| vector.into_iter().collect())
+    }
+
+    /// Get map value of key in section
+    ///
+    /// The value is split into pairs by `pair_sep` and every pair into key and value by the first
+    /// occurrence of `kv_sep`; surrounding whitespace is trimmed. Empty pairs (e.g. after a
+    /// trailing separator) are skipped, a pair without `kv_sep` maps to an empty value.
+    ///
+    /// Note that `;` starts a comment in ini files, so such values may only be constructed in
+    /// program.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nopts = a:1, b:2, c");
+    /// let opts = conf.get_map("section", "opts", ",", ":").unwrap();
+    /// assert_eq!(opts["a"], "1");
+    /// assert_eq!(opts["b"], "2");
+    /// assert_eq!(opts["c"], "");
+    /// ```
+    pub fn get_map(
+        &self,
+        section: &str,
+        key: &str,
+        pair_sep: &str,
+        kv_sep: &str,
+    ) -> Option<HashMap<String, String>> {
+        self.get_raw(section, key).map(|x| {
+            x.split(pair_sep)
+                .map(str::trim)
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let mut kv = pair.splitn(2, kv_sep);
+                    let key = kv.next().unwrap_or("").trim().to_owned();
+                    let value = kv.next().unwrap_or("").trim().to_owned();
+                    (key, value)
+                })
+                .collect()
+        })
+    }
+
+    /// Get format recorded for a list value added by
+    /// [`item_vec_with_sep()`](#method.item_vec_with_sep)
+    ///
+    /// The format is dropped whenever the value is changed by other means. It is not written
+    /// on serialization, so after reading the config back the separator has to be passed to
+    /// [`get_vec_with_sep()`](#method.get_vec_with_sep).
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::new().section("default").item_vec_with_sep("a", &[1, 2], "|");
+    /// assert_eq!(conf.vec_format("default", "a").unwrap().separator(), "|");
+    /// ```
+    pub fn vec_format(&self, section: &str, key: &str) -> Option<&VecFormat> {
+        self.vec_formats.get(&(section.to_owned(), key.to_owned()))
+    }
+
+    /// Separator recorded for key in section or comma by default
+    fn vec_separator(&self, section: &str, key: &str) -> &str {
+        match self.vec_format(section, key) {
+            Some(format) => format.separator(),
+            None => ",",
+        }
+    }
+
+    /// Get vector value of key in section separeted by sep string
+    ///
+    /// The separator may consist of several characters, e.g. `" | "`; it must match exactly, so
+    /// elements may contain its parts. An empty separator yields the whole value as one element.
+    /// Separators preceded by backslash, as written by
+    /// [`item_vec_with_sep()`](#method.item_vec_with_sep), are part of the element.
+    /// The function returns `None` if one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1|2|3|4\npipes = a|b | c");
+    /// let value: Option<Vec<u8>> = conf.get_vec_with_sep("section", "list", "|");
+    /// let pipes: Option<Vec<String>> = conf.get_vec_with_sep("section", "pipes", " | ");
+    /// assert_eq!(value, Some(vec![1, 2, 3, 4]));
+    /// assert_eq!(pipes, Some(vec!["a|b".to_owned(), "c".to_owned()]));
+    /// ```
+    pub fn get_vec_with_sep<T>(&self, section: &str, key: &str, sep: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        self.vec_elements(section, key, sep).and_then(|elements| {
+            elements
+                .map(|element| element.parse())
+                .collect::<Result<Vec<T>, _>>()
+                .ok()
+        })
+    }
+
+    /// Get element at `index` of vector value of key in section separated by sep string
+    ///
+    /// Elements are split lazily like in [`get_vec_with_sep()`](#method.get_vec_with_sep), and
+    /// only the requested one is parsed. The function returns `None` if `index` is out of range
+    /// or the element can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 10, 20, 30");
+    /// let value: Option<u8> = conf.get_vec_at("section", "list", 1, ",");
+    /// assert_eq!(value, Some(20));
+    /// ```
+    pub fn get_vec_at<T>(&self, section: &str, key: &str, index: usize, sep: &str) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.vec_elements(section, key, sep)?
+            .nth(index)
+            .and_then(|element| element.parse().ok())
+    }
+
+    /// Iterate over elements of vector value of key in section separated by sep string
+    ///
+    /// This is the lazy counterpart of [`get_vec_with_sep()`](#method.get_vec_with_sep): elements
+    /// are split and trimmed in the same way, but yielded as slices of the stored value without
+    /// allocation, so escaped separators are kept as written.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nlist = 1, 2, 3");
+    /// let sum: u32 = conf
+    ///     .iter_vec("section", "list", ",")
+    ///     .unwrap()
+    ///     .map(|x| x.parse::<u32>().unwrap())
+    ///     .sum();
+    /// assert_eq!(sum, 6);
+    /// ```
+    pub fn iter_vec<'a>(
+        &'a self,
+        section: &str,
+        key: &str,
+        sep: &'a str,
+    ) -> Option<impl Iterator<Item = &'a str> + 'a> {
+        self.get_raw(section, key)
+            .map(|x| split_unescaped(x, sep).map(str::trim))
+    }
+
+    /// Elements of vector value of key in section with escaped separators decoded
+    fn vec_elements<'a>(
+        &'a self,
+        section: &str,
+        key: &str,
+        sep: &'a str,
+    ) -> Option<impl Iterator<Item = Cow<'a, str>> + 'a> {
+        self.iter_vec(section, key, sep)
+            .map(move |elements| elements.map(move |element| unescape_element(element, sep)))
+    }
+
+    /// Get vector value of key in section with CSV-style quoting
+    ///
+    /// Elements are separated by comma. Elements in double quotes may contain commas, and `""`
+    /// inside them stands for a literal quote; whitespace outside quotes is trimmed. Every
+    /// separator delimits an element, so `"", "a", ""` yields three elements, two of them empty.
+    /// The function returns `None` for unterminated quotes, text after a closing quote or if one
+    /// of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer(r#"[section]
+    /// names = "Smith, John", "Doe, ""JD"" Jane", Roe"#);
+    /// let value: Vec<String> = conf.get_vec_csv("section", "names").unwrap();
+    /// assert_eq!(value, ["Smith, John", r#"Doe, "JD" Jane"#, "Roe"]);
+    /// ```
+    pub fn get_vec_csv<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        split_csv(self.get_raw(section, key)?)?
+            .iter()
+            .map(|x| x.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .ok()
+    }
+
+    /// Get vector value of key in section written as JSON array, e.g. `[1, 2]` or `["a", "b"]`
+    ///
+    /// Quoted elements may contain commas and JSON escapes like `\"` or `\u00e9`, unquoted
+    /// elements are parsed as they are. The function returns `None` if the value is no array
+    /// or one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer(r#"[section]
+    /// ports = [80, 443]
+    /// names = ["Smith, John", "say \"hi\""]"#);
+    /// let ports: Vec<u16> = conf.get_json_array("section", "ports").unwrap();
+    /// assert_eq!(ports, [80, 443]);
+    /// let names: Vec<String> = conf.get_json_array("section", "names").unwrap();
+    /// assert_eq!(names, ["Smith, John", "say \"hi\""]);
+    /// ```
+    pub fn get_json_array<T>(&self, section: &str, key: &str) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        split_json_array(self.get_raw(section, key)?)?
+            .iter()
+            .map(|x| x.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .ok()
+    }
+
+    /// Get vector value of key in section, trimming any of `chars` from both ends of each element
+    ///
+    /// The value is split like in [`get_vec()`](#method.get_vec). Surrounding whitespace is
+    /// removed before trimming, so an element consisting only of trimmed characters (e.g. `""`)
+    /// becomes an empty string.
+    /// The function returns `None` if one of the elements can not be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer(r#"[section]
+    /// names = "Alice", 'Bob', """#);
+    /// let value: Vec<String> = conf.get_vec_trimmed("section", "names", &['"', '\'']).unwrap();
+    /// assert_eq!(value, ["Alice", "Bob", ""]);
+    /// ```
+    pub fn get_vec_trimmed<T>(&self, section: &str, key: &str, chars: &[char]) -> Option<Vec<T>>
+    where
+        T: FromStr,
+    {
+        let sep = self.vec_separator(section, key);
+        self.vec_elements(section, key, sep).and_then(|elements| {
+            elements
+                .map(|element| element.trim_matches(chars).parse())
+                .collect::<Result<Vec<T>, _>>()
+                .ok()
+        })
+    }
+
+    /// Canonicalize the Ini in place
+    ///
+    /// * whitespace is trimmed from both ends of section names, keys and values; names which
+    ///   become equal are merged, the later value wins;
+    /// * sections without keys are removed together with their comments;
+    /// * order of sections and keys is preserved.
+    ///
+    /// Serialization always writes `key = value`, so the spacing around separators is consistent
+    /// after the next [`to_buffer()`](#method.to_buffer) anyway.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section(" section ").item("  one", "1  ");
+    /// conf.normalize();
+    /// assert_eq!(conf.to_buffer(), "[section]\none = 1");
+    /// ```
+    pub fn normalize(&mut self) {
+        self.modified = true;
+        let mut data = IniParsed::new();
+        for (section, entries) in self.data.iter() {
+            if entries.is_empty() {
+                continue;
+            }
+            let normalized = data.entry(section.trim().to_owned()).or_default();
+            for (key, value) in entries {
+                normalized.insert(key.trim().to_owned(), value.trim().to_owned());
+            }
+        }
+        self.data = data;
+        fn trim_keys<V>(((section, key), value): ((String, String), V)) -> ((String, String), V) {
+            ((section.trim().to_owned(), key.trim().to_owned()), value)
+        }
+        self.vec_formats = self.vec_formats.drain().map(trim_keys).collect();
+        self.comments = self.comments.drain().map(trim_keys).collect();
+        // comments of removed (or never filled) sections are dropped
+        let data = &self.data;
+        self.section_comments = self
+            .section_comments
+            .drain()
+            .map(|(section, text)| (section.trim().to_owned(), text))
+            .filter(|(section, _)| data.contains_key(section))
+            .collect();
+        self.inline_comments = self.inline_comments.drain().map(trim_keys).collect();
+    }
+
+    /// Replace all keys of section with `entries` in the given order, creating the section if
+    /// needed
+    ///
+    /// An existing section keeps its position. Comments and list formats of the previous keys
+    /// are dropped.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// conf.replace_section("a", &[("z", "3"), ("w", "4")]);
+    /// assert_eq!(conf.to_buffer(), "[a]\nz = 3\nw = 4\n\n[b]\ny = 2");
+    /// ```
+    pub fn replace_section(&mut self, name: &str, entries: &[(&str, &str)]) {
+        self.modified = true;
+        let mut section = Section::with_capacity(entries.len());
+        for (key, value) in entries {
+            section.insert((*key).to_owned(), (*value).to_owned());
+        }
+        *self.data.entry(name.to_owned()).or_default() = section;
+        self.vec_formats.retain(|(section, _), _| section != name);
+        self.comments.retain(|(section, _), _| section != name);
+        self.inline_comments
+            .retain(|(section, _), _| section != name);
+    }
+
+    /// Copy section into a new Ini, preserving key order
+    ///
+    /// Comments and list formats of the keys are copied as well.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// let b = conf.extract_section("b").unwrap();
+    /// assert_eq!(b.to_buffer(), "[b]\ny = 2");
+    /// ```
+    pub fn extract_section(&self, name: &str) -> Option<Ini> {
+        let section = self.data.get(name)?;
+        let mut result = Ini::new();
+        let target = result.data.entry(name.to_owned()).or_default();
+        for (key, value) in section {
+            target.insert(key.clone(), value.clone());
+        }
+        fn in_section<V: Clone>(
+            map: &HashMap<(String, String), V>,
+            name: &str,
+        ) -> HashMap<(String, String), V> {
+            map.iter()
+                .filter(|((section, _), _)| section == name)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        }
+        result.vec_formats = in_section(&self.vec_formats, name);
+        result.comments = in_section(&self.comments, name);
+        if let Some(text) = self.section_comments.get(name) {
+            result
+                .section_comments
+                .insert(name.to_owned(), text.clone());
+        }
+        result.inline_comments = in_section(&self.inline_comments, name);
+        result.escape_values = self.escape_values;
+        result.line_ending = self.line_ending;
+        result.section_header = self.section_header;
+        result.last_section_name = name.to_owned();
+        Some(result)
+    }
+
+    /// Remove all sections without keys and return their number
+    ///
+    /// Comments and list formats recorded for the removed sections are dropped as well.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// for (_, section) in conf.sections_mut() {
+    ///     section.remove("y");
+    /// }
+    /// assert_eq!(conf.trim_empty_sections(), 1);
+    /// assert_eq!(conf.to_buffer(), "[a]\nx = 1");
+    /// ```
+    pub fn trim_empty_sections(&mut self) -> usize {
+        let empty: Vec<String> = self
+            .data
+            .iter()
+            .filter(|(_, entries)| entries.is_empty())
+            .map(|(section, _)| section.clone())
+            .collect();
+        for section in &empty {
+            self.data.remove(section);
+            self.section_comments.remove(section);
+        }
+        let is_kept = |(section, _): &(String, String)| !empty.contains(section);
+        self.vec_formats.retain(|key, _| is_kept(key));
+        self.comments.retain(|key, _| is_kept(key));
+        self.inline_comments.retain(|key, _| is_kept(key));
+        self.modified |= !empty.is_empty();
+        empty.len()
+    }
+
+    /// Prepend `prefix` to the name of every section, preserving order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[server]\nport = 80");
+    /// conf.prefix_sections("web.");
+    /// let port: Option<u16> = conf.get("web.server", "port");
+    /// assert_eq!(port, Some(80));
+    /// ```
+    pub fn prefix_sections(&mut self, prefix: &str) {
+        self.rename_sections(|name| format!("{}{}", prefix, name));
+    }
+
+    /// Remove `prefix` from the name of every section starting with it, preserving order
+    ///
+    /// Sections whose names become equal are merged, the later value of a key wins.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[web.server]\nport = 80\n[db]\nport = 5432");
+    /// conf.strip_prefix("web.");
+    /// assert_eq!(conf.to_buffer(), "[server]\nport = 80\n\n[db]\nport = 5432");
+    /// ```
+    pub fn strip_prefix(&mut self, prefix: &str) {
+        self.rename_sections(|name| name.strip_prefix(prefix).unwrap_or(name).to_owned());
+    }
+
+    /// Copy section `source` with its comments to a new section `dest` appended at the end
+    ///
+    /// Returns `false` and changes nothing if `source` doesn't exist or `dest` already exists.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[server.default]\nport = 80");
+    /// assert!(conf.clone_section_as("server.default", "server.prod"));
+    /// conf.insert("server.prod", "port", "443");
+    /// assert_eq!(
+    ///     conf.to_buffer(),
+    ///     "[server.default]\nport = 80\n\n[server.prod]\nport = 443"
+    /// );
+    /// ```
+    pub fn clone_section_as(&mut self, source: &str, dest: &str) -> bool {
+        if self.data.contains_key(dest) {
+            return false;
+        }
+        let mut part = match self.extract_section(source) {
+            Some(part) => part,
+            None => return false,
+        };
+        part.rename_sections(|_| dest.to_owned());
+        self.merge(&part);
+        true
+    }
+
+    fn rename_sections<F: Fn(&str) -> String>(&mut self, rename: F) {
+        self.modified = true;
+        let mut data = IniParsed::new();
+        for (section, entries) in self.data.iter() {
+            let renamed = data.entry(rename(section)).or_default();
+            for (key, value) in entries {
+                renamed.insert(key.clone(), value.clone());
+            }
+        }
+        self.data = data;
+        self.last_section_name = rename(&self.last_section_name);
+        self.vec_formats = self
+            .vec_formats
+            .drain()
+            .map(|((section, key), value)| ((rename(&section), key), value))
+            .collect();
+        self.comments = self
+            .comments
+            .drain()
+            .map(|((section, key), value)| ((rename(&section), key), value))
+            .collect();
+        self.section_comments = self
+            .section_comments
+            .drain()
+            .map(|(section, text)| (rename(&section), text))
+            .collect();
+        self.inline_comments = self
+            .inline_comments
+            .drain()
+            .map(|((section, key), value)| ((rename(&section), key), value))
+            .collect();
+    }
+
+    /// Set order in which keys of section are written
+    ///
+    /// Keys listed in `order` are moved to the beginning of the section in the given sequence,
+    /// other keys follow in their current relative order. Unknown keys are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[user]\nage = 42\nname = John");
+    /// conf.set_key_order("user", &["name"]);
+    /// assert_eq!(conf.to_buffer(), "[user]\nname = John\nage = 42");
+    /// ```
+    pub fn set_key_order(&mut self, section: &str, order: &[&str]) {
+        if let Some(section) = self.data.get_mut(section) {
+            let before: Vec<String> = section.keys().cloned().collect();
+            section.set_order(order);
+            self.modified |= section.keys().ne(before.iter());
+        }
+    }
+
+    /// Move section directly before `target` section
+    ///
+    /// Returns `false` if either section is missing.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// assert!(conf.move_section_before("b", "a"));
+    /// assert_eq!(conf.to_buffer(), "[b]\ny = 2\n\n[a]\nx = 1");
+    /// ```
+    pub fn move_section_before(&mut self, section: &str, target: &str) -> bool {
+        let moved = self.data.move_before(section, target);
+        self.modified |= moved;
+        moved
+    }
+
+    /// Move section directly after `target` section
+    ///
+    /// Returns `false` if either section is missing.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// assert!(conf.move_section_after("a", "b"));
+    /// assert_eq!(conf.to_buffer(), "[b]\ny = 2\n\n[a]\nx = 1");
+    /// ```
+    pub fn move_section_after(&mut self, section: &str, target: &str) -> bool {
+        let moved = self.data.move_after(section, target);
+        self.modified |= moved;
+        moved
+    }
+
+    /// Set order in which sections are written
+    ///
+    /// Sections listed in `order` are moved to the beginning in the given sequence, other
+    /// sections follow in their current relative order. Unknown sections are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1\n[b]\ny = 2");
+    /// conf.set_section_order(&["b"]);
+    /// assert_eq!(conf.to_buffer(), "[b]\ny = 2\n\n[a]\nx = 1");
+    /// ```
+    pub fn set_section_order(&mut self, order: &[&str]) {
+        let before: Vec<String> = self.data.keys().cloned().collect();
+        self.data.set_order(order);
+        self.modified |= self.data.keys().ne(before.iter());
+    }
+
+    /// Get comment attached to key in section
+    ///
+    /// Comment lines directly preceding a key are attached to it during parsing and written
+    /// above it on serialization. Multiple lines are joined with `\n`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\n; first\n; second\none = 1");
+    /// assert_eq!(conf.comment_for("section", "one"), Some("first\nsecond"));
+    /// ```
+    pub fn comment_for(&self, section: &str, key: &str) -> Option<&str> {
+        self.comments
+            .get(&(section.to_owned(), key.to_owned()))
+            .map(String::as_str)
+    }
+
+    /// Attach comment to key in section, replacing the previous one
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("section").item("one", "1");
+    /// conf.set_comment("section", "one", "the first");
+    /// assert_eq!(conf.to_buffer(), "[section]\n; the first\none = 1");
+    /// ```
+    pub fn set_comment<S: Into<String>>(&mut self, section: &str, key: &str, text: S) {
+        self.modified = true;
+        self.comments
+            .insert((section.to_owned(), key.to_owned()), text.into());
+    }
+
+    /// Get comment attached to section
+    ///
+    /// Comment lines directly preceding a section header are attached to the section during
+    /// parsing and written above the header on serialization. Multiple lines are joined with
+    /// `\n`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("; database\n; settings\n[db]\nport = 5432");
+    /// assert_eq!(conf.section_comment("db"), Some("database\nsettings"));
+    /// ```
+    pub fn section_comment(&self, section: &str) -> Option<&str> {
+        self.section_comments.get(section).map(String::as_str)
+    }
+
+    /// Attach comment to section, replacing the previous one
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("db").item("port", "5432");
+    /// conf.set_section_comment("db", "database");
+    /// assert_eq!(conf.to_buffer(), "; database\n[db]\nport = 5432");
+    /// ```
+    pub fn set_section_comment<S: Into<String>>(&mut self, section: &str, text: S) {
+        self.modified = true;
+        self.section_comments
+            .insert(section.to_owned(), text.into());
+    }
+
+    /// Get comment following the value of key in section on the same line
+    ///
+    /// Such comments are only kept if
+    /// [`inline_comments`](struct.ParseOptions.html#method.inline_comments) is set. They are
+    /// written after the value on serialization.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, ParseOptions};
+    /// let options = ParseOptions::new().inline_comments(true);
+    /// let conf = Ini::from_buffer_with_options("[server]\nport = 8080 ; production", &options).unwrap();
+    /// assert_eq!(conf.inline_comment_for("server", "port"), Some("production"));
+    /// assert_eq!(conf.to_buffer(), "[server]\nport = 8080 ; production");
+    /// ```
+    pub fn inline_comment_for(&self, section: &str, key: &str) -> Option<&str> {
+        self.inline_comments
+            .get(&(section.to_owned(), key.to_owned()))
+            .map(String::as_str)
+    }
+
+    /// Attach inline comment to key in section, replacing the previous one
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("server").item("port", "8080");
+    /// conf.set_inline_comment("server", "port", "production");
+    /// assert_eq!(conf.to_buffer(), "[server]\nport = 8080 ; production");
+    /// ```
+    pub fn set_inline_comment<S: Into<String>>(&mut self, section: &str, key: &str, text: S) {
+        self.modified = true;
+        self.inline_comments
+            .insert((section.to_owned(), key.to_owned()), text.into());
+    }
+
+    /// Iterate over a section by a name
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer(["[search]",
+    ///                         "g = google.com",
+    ///                         "dd = duckduckgo.com"].join("\n"));
+    /// let search = conf.iter_section("search").unwrap();
+    /// for (k, v) in search {
+    ///   println!("key: {} value: {}", k, v);
+    /// }
+    /// ```
+    pub fn iter_section(&self, section: &str) -> Option<SectionIter<'_>> {
+        self.data.get(section).map(|value| value.iter())
+    }
+
+    /// Iterate over a section which must exist
+    ///
+    /// Like [`iter_section()`](#method.iter_section), but returns an error naming the section
+    /// if it doesn't exist, so that it can be propagated with `?`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[db]\nhost = localhost");
+    /// assert_eq!(conf.require_section("db").unwrap().count(), 1);
+    /// let err = conf.require_section("log").err().unwrap();
+    /// assert_eq!(err.to_string(), "missing required section log");
+    /// ```
+    pub fn require_section(&self, section: &str) -> Result<SectionIter<'_>, MissingSection> {
+        self.iter_section(section)
+            .ok_or_else(|| MissingSection(section.to_owned()))
+    }
+
+    /// Collect entries of a section into owned key-value pairs in insertion order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\nb = 2\na = 1");
+    /// let pairs = conf.section_pairs("section").unwrap();
+    /// assert_eq!(pairs, [("b".to_owned(), "2".to_owned()), ("a".to_owned(), "1".to_owned())]);
+    /// ```
+    pub fn section_pairs(&self, section: &str) -> Option<Vec<(String, String)>> {
+        self.iter_section(section)
+            .map(|iter| iter.map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    /// Count keys in all sections
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[a]\nx = 1\ny = 2\n[b]\nz = 3");
+    /// assert_eq!(conf.total_keys(), 3);
+    /// ```
+    pub fn total_keys(&self) -> usize {
+        self.data.iter().map(|(_, section)| section.len()).sum()
+    }
+
+    /// Count keys in section, `0` if the section is missing
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[a]\nx = 1\ny = 2\n[b]\nz = 3");
+    /// assert_eq!(conf.total_keys_in("a"), 2);
+    /// assert_eq!(conf.total_keys_in("c"), 0);
+    /// ```
+    pub fn total_keys_in(&self, section: &str) -> usize {
+        self.data.get(section).map_or(0, |section| section.len())
+    }
+
+    /// Find first key in section holding `value`
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[colors]\nsky = blue\nsea = blue");
+    /// assert_eq!(conf.find_key_by_value("colors", "blue").unwrap(), "sky");
+    /// assert_eq!(conf.find_key_by_value("colors", "red"), None);
+    /// ```
+    pub fn find_key_by_value(&self, section: &str, value: &str) -> Option<&String> {
+        self.iter_section(section)?
+            .find(|(_, v)| *v == value)
+            .map(|(k, _)| k)
+    }
+
+    /// Check if any key in section holds `value`
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[colors]\nsky = blue");
+    /// assert!(conf.section_contains_value("colors", "blue"));
+    /// assert!(!conf.section_contains_value("colors", "red"));
+    /// ```
+    pub fn section_contains_value(&self, section: &str, value: &str) -> bool {
+        self.find_key_by_value(section, value).is_some()
+    }
+
+    /// Keys in section starting with `prefix`, in order; a trailing `*` in `prefix` is ignored
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[flags]\nfeature.a = on\nlog = off\nfeature.b = off");
+    /// assert_eq!(conf.keys_matching("flags", "feature."), ["feature.a", "feature.b"]);
+    /// assert_eq!(conf.keys_matching("flags", "feature.*"), ["feature.a", "feature.b"]);
+    /// ```
+    pub fn keys_matching(&self, section: &str, prefix: &str) -> Vec<&String> {
+        let prefix = prefix.strip_suffix('*').unwrap_or(prefix);
+        self.iter_section(section)
+            .map(|iter| {
+                iter.map(|(k, _)| k)
+                    .filter(|k| k.starts_with(prefix))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Iterate over all sections, yielding pairs of section name and iterator
+    /// over the section elements. The concrete iterator element type is
+    /// `(&'a String, ordered_hashmap::Iter<'a, String, String>)`.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::new().section("foo")
+    ///                      .item("item", "value")
+    ///                      .item("other", "something")
+    ///                      .section("bar")
+    ///                      .item("one", "1");
+    /// for (section, iter) in conf.iter() {
+    ///   for (key, val) in iter {
+    ///     println!("section: {} key: {} val: {}", section, key, val);
+    ///   }
+    /// }
+    pub fn iter(&self) -> IniIter<'_> {
+        IniIter {
+            iter: self.data.iter(),
+        }
+    }
+
+    /// Iterate over entries with values which are not empty after trimming, yielding triples of
+    /// section name, key and value in order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[db]\nhost = localhost\npassword =\n[log]\nfile =  ");
+    /// let set: Vec<_> = conf.iter_set().collect();
+    /// assert_eq!(set, [(&"db".to_owned(), &"host".to_owned(), &"localhost".to_owned())]);
+    /// ```
+    pub fn iter_set(&self) -> impl Iterator<Item = (&String, &String, &String)> {
+        self.iter()
+            .flat_map(|(section, iter)| iter.map(move |(key, value)| (section, key, value)))
+            .filter(|(_, _, value)| !value.trim().is_empty())
+    }
+
+    /// Iterate over all sections, yielding pairs of section name and mutable
+    /// iterator over the section elements. The concrete iterator element type is
+    /// `(&'a String, ordered_hashmap::IterMut<'a, String, String>)`.
+    ///
+    /// Drops all list formats recorded by
+    /// [`item_vec_with_sep()`](#method.item_vec_with_sep).
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("foo")
+    ///                          .item("item", "value")
+    ///                          .item("other", "something")
+    ///                          .section("bar")
+    ///                          .item("one", "1");
+    /// for (section, iter_mut) in conf.iter_mut() {
+    ///   for (key, val) in iter_mut {
+    ///     *val = String::from("replaced");
+    ///   }
+    /// }
+    pub fn iter_mut(&mut self) -> IniIterMut<'_> {
+        self.modified = true;
+        // values may change, so recorded list formats can't be trusted anymore
+        self.vec_formats.clear();
+        IniIterMut {
+            iter: self.data.iter_mut(),
+        }
+    }
+
+    /// Borrow Ini as a read-only view
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::{Ini, IniView};
+    /// fn port(conf: IniView) -> Option<u16> {
+    ///     conf.get("server", "port")
+    /// }
+    /// let conf = Ini::from_buffer("[server]\nport = 80");
+    /// assert_eq!(port(conf.view()), Some(80));
+    /// ```
+    pub fn view(&self) -> IniView<'_> {
+        IniView { ini: self }
+    }
+
+    /// Iterate over all sections in order, yielding pairs of section name and mutable section
+    ///
+    /// Unlike [`iter_mut()`](#method.iter_mut), this allows to add or remove keys of every
+    /// section during iteration. Drops all list formats recorded by
+    /// [`item_vec_with_sep()`](#method.item_vec_with_sep).
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::new().section("foo").item("a", "1").section("bar").item("b", "2");
+    /// for (name, section) in conf.sections_mut() {
+    ///     section.remove("a");
+    ///     section.insert("name".to_owned(), name.clone());
+    /// }
+    /// assert_eq!(conf.to_buffer(), "[foo]\nname = foo\n\n[bar]\nb = 2\nname = bar");
+    /// ```
+    pub fn sections_mut(&mut self) -> impl Iterator<Item = (&String, &mut Section)> + '_ {
+        self.modified = true;
+        // values may change, so recorded list formats can't be trusted anymore
+        self.vec_formats.clear();
+        self.data.iter_mut_ordered()
+    }
+
+    /// Shrink capacity of all sections and internal collections as much as possible
+    ///
+    /// Useful for long-lived configs after many keys were removed.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[section]\none = 1\ntwo = 2");
+    /// for (_, section) in conf.sections_mut() {
+    ///     section.remove("two");
+    /// }
+    /// conf.shrink_to_fit();
+    /// assert_eq!(conf.to_buffer(), "[section]\none = 1");
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        for (_, section) in self.data.iter_mut() {
+            section.shrink_to_fit();
+        }
+        self.data.shrink_to_fit();
+        self.vec_formats.shrink_to_fit();
+        self.comments.shrink_to_fit();
+        self.section_comments.shrink_to_fit();
+        self.inline_comments.shrink_to_fit();
+        self.merged_sections.shrink_to_fit();
+    }
+
+    /// Override values with environment variables
+    ///
+    /// For every key, a variable named `PREFIX_SECTION_KEY` is looked up, where the prefix,
+    /// section name and key are uppercased and every character except ASCII letters and digits
+    /// is replaced with `_`. An empty prefix is omitted along with its `_`. Only existing keys
+    /// are overridden; variables with invalid unicode are ignored.
+    ///
+    /// Available with the `std` feature (enabled by default).
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// std::env::set_var("MYAPP_SERVER_LISTEN_PORT", "8080");
+    /// let mut conf = Ini::from_buffer("[server]\nlisten-port = 80");
+    /// conf.apply_env_overrides("myapp");
+    /// let port: Option<u16> = conf.get("server", "listen-port");
+    /// assert_eq!(port, Some(8080));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn apply_env_overrides(&mut self, prefix: &str) {
+        fn mangle(name: &str) -> String {
+            name.chars()
+                .map(|c| match c {
+                    c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+                    _ => '_',
+                })
+                .collect()
+        }
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}_", mangle(prefix))
+        };
+        self.update_values(|section, key, _| {
+            std::env::var(format!("{}{}_{}", prefix, mangle(section), mangle(key))).ok()
+        });
+    }
+
+    /// Replace every value with the result of `f`, which receives section name, key and current
+    /// value. Order of sections and keys is preserved.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[section]\nname =   padded  ");
+    /// conf.map_values(|_, _, value| value.trim().to_owned());
+    /// assert_eq!(conf.to_buffer(), "[section]\nname = padded");
+    /// ```
+    pub fn map_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &str, &str) -> String,
+    {
+        self.update_values(|section, key, value| Some(f(section, key, value)));
+    }
+
+    /// Replace values for which `f` returns `Some`, dropping recorded list formats of changed
+    /// values
+    fn update_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &str, &str) -> Option<String>,
+    {
+        self.modified = true;
+        for (section, entries) in self.data.iter_mut() {
+            for (key, value) in entries.iter_mut() {
+                if let Some(new_value) = f(section, key, value) {
+                    if new_value != *value {
+                        self.vec_formats.remove(&(section.clone(), key.clone()));
+                    }
+                    *value = new_value;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.serialize(|_| true))
+    }
+}
+
+/// Builder for Ini with non-consuming methods
+///
+/// Unlike the method chaining on [`Ini`](struct.Ini.html) itself, the builder methods take
+/// `&mut self`, so items may be added conditionally without reassigning the builder.
+#[derive(Debug, Default)]
+pub struct IniBuilder {
+    ini: Ini,
+}
+
+impl IniBuilder {
+    /// Create an empty builder
+    pub fn new() -> IniBuilder {
+        IniBuilder { ini: Ini::new() }
+    }
+
+    /// Set section name for following [`item()`](#method.item)s
+    pub fn section<S: Into<String>>(&mut self, name: S) -> &mut Self {
+        self.ini.last_section_name = name.into();
+        self
+    }
+
+    /// Add key-value pair to last section
+    pub fn item<S: Into<String>>(&mut self, name: S, value: S) -> &mut Self {
+        let section = self.ini.last_section_name.clone();
+        self.ini.insert(&section, name, value);
+        self
+    }
+
+    /// Finish building and return the constructed Ini
+    pub fn build(self) -> Ini {
+        self.ini
+    }
+}
+
+/// Read-only view of Ini returned by [`Ini::view()`](struct.Ini.html#method.view)
+///
+/// The view only exposes getters, so it states in an API that the config is not modified.
+#[derive(Debug, Clone, Copy)]
+pub struct IniView<'a> {
+    ini: &'a Ini,
+}
+
+impl<'a> IniView<'a> {
+    /// Get scalar value of key in section, see [`Ini::get()`](struct.Ini.html#method.get)
+    pub fn get<T: FromStr>(&self, section: &str, key: &str) -> Option<T> {
+        self.ini.get(section, key)
+    }
+
+    /// Get vector value of key in section, see [`Ini::get_vec()`](struct.Ini.html#method.get_vec)
+    pub fn get_vec<T: FromStr>(&self, section: &str, key: &str) -> Option<Vec<T>> {
+        self.ini.get_vec(section, key)
+    }
+
+    /// Iterate over all sections, see [`Ini::iter()`](struct.Ini.html#method.iter)
+    pub fn iter(&self) -> IniIter<'a> {
+        self.ini.iter()
+    }
+
+    /// Iterate over section names in order
+    pub fn sections(&self) -> impl Iterator<Item = &'a str> {
+        self.ini.data.keys().map(String::as_str)
+    }
+}
+
+impl<'a> Index<&'a str> for Ini {
+    type Output = Section;
+
+    /// Get section by name for read-only access
+    ///
+    /// Indexing the section by key yields the value of the key as stored, without going through
+    /// the read path of [`get()`](#method.get): list escapes are not decoded and references
+    /// are not resolved.
+    ///
+    /// # Panics
+    /// Panics if the section (or the key in the section) is not present in the Ini, naming the
+    /// missing section or key. Use [`get()`](#method.get) if you are not sure.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let conf = Ini::from_buffer("[section]\none = 1");
+    /// assert_eq!(conf["section"]["one"], "1");
+    /// ```
+    fn index(&self, section: &'a str) -> &Self::Output {
+        match self.data.get(section) {
+            Some(entries) => entries,
+            None => panic!("section not found: {:?}", section),
+        }
+    }
+}
+
+impl Default for Ini {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<HashMap<String, HashMap<String, String>>> for Ini {
+    /// Construct Ini from map of sections to maps of keys to values
+    ///
+    /// `HashMap` has no order, so sections and keys are sorted by name.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use tini::Ini;
+    /// let mut section = HashMap::new();
+    /// section.insert("b".to_owned(), "2".to_owned());
+    /// section.insert("a".to_owned(), "1".to_owned());
+    /// let mut map = HashMap::new();
+    /// map.insert("section".to_owned(), section);
+    /// let conf = Ini::from(map);
+    /// assert_eq!(conf.to_buffer(), "[section]\na = 1\nb = 2");
+    /// ```
+    fn from(map: HashMap<String, HashMap<String, String>>) -> Self {
+        let mut sections: Vec<_> = map.into_iter().collect();
+        sections.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut result = Ini::new();
+        for (section, entries) in sections {
+            let mut entries: Vec<_> = entries.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            let target = result.data.entry(section).or_default();
+            for (key, value) in entries {
+                target.insert(key, value);
+            }
+        }
+        result
+    }
+}
+
+impl Extend<(String, String, String)> for Ini {
+    /// Add `(section, key, value)` entries like [`insert()`](struct.Ini.html#method.insert)
+    ///
+    /// New sections and keys are appended in order of appearance, values of existing keys are
+    /// replaced in place.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::Ini;
+    /// let mut conf = Ini::from_buffer("[a]\nx = 1");
+    /// conf.extend(vec![
+    ///     ("b".to_owned(), "y".to_owned(), "2".to_owned()),
+    ///     ("a".to_owned(), "x".to_owned(), "3".to_owned()),
+    /// ]);
+    /// assert_eq!(conf.to_buffer(), "[a]\nx = 3\n\n[b]\ny = 2");
+    /// ```
+    fn extend<I: IntoIterator<Item = (String, String, String)>>(&mut self, iter: I) {
+        for (section, key, value) in iter {
+            self.insert(&section, key, value);
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct IniIter<'a> {
+    iter: ordered_hashmap::Iter<'a, String, Section>,
+}
+
+impl<'a> Iterator for IniIter<'a> {
+    type Item = (&'a String, SectionIter<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|(string, section)| (string, section.iter()))
+    }
+}
+
+#[doc(hidden)]
+pub struct IniIterMut<'a> {
+    iter: ordered_hashmap::IterMut<'a, String, Section>,
+}
+
+impl<'a> Iterator for IniIterMut<'a> {
+    type Item = (&'a String, SectionIterMut<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|(string, section)| (string, section.iter_mut()))
+    }
+}
+
+/// Integer types parsed by [`Ini::get_int_radix()`](struct.Ini.html#method.get_int_radix)
+pub trait FromStrRadix: Sized {
+    /// Parse integer from string of digits in given radix, see `i32::from_str_radix()`
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError>;
+}
+
+macro_rules! impl_from_str_radix {
+    ($($t:ty),+) => {
+        $(impl FromStrRadix for $t {
+            fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
+                <$t>::from_str_radix(src, radix)
+            }
+        })+
+    };
+}
+
+impl_from_str_radix!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Quote and escape string for JSON
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Write key bare if allowed in TOML, quoted otherwise; dots are kept bare in section names
+fn toml_key(key: &str, dots: bool) -> String {
+    let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-' || (dots && c == '.');
+    if !key.is_empty() && key.chars().all(bare) && !(dots && key.split('.').any(str::is_empty)) {
+        key.to_owned()
+    } else {
+        json_string(key)
+    }
+}
+
+/// Check if value is a TOML integer or float in decimal notation
+fn is_toml_number(value: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    // TOML forbids leading zeros in integer part
+    let integer = |s: &str| digits(s) && (s == "0" || !s.starts_with('0'));
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((int, frac)) => integer(int) && digits(frac),
+        None => integer(mantissa),
+    };
+    let exponent_ok = exponent.map_or(true, |e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+    mantissa_ok && exponent_ok
+}
+
+/// Get tuple value of key in section with element types given in order
+///
+/// The value is split like in [`Ini::get_vec()`](struct.Ini.html#method.get_vec). Evaluates to
+/// `None` if the number of elements differs from the number of types or one of the elements can
+/// not be parsed.
+///
+/// # Example
+/// ```
+/// # use tini::{get_tuple, Ini};
+/// let conf = Ini::from_buffer("[section]\npoint = 3, 4.5, hello");
+/// let point = get_tuple!(conf, "section", "point", i32, f64, String);
+/// assert_eq!(point, Some((3, 4.5, "hello".to_owned())));
+///
+/// let pair = get_tuple!(conf, "section", "point", i32, f64);
+/// assert_eq!(pair, None);
+/// ```
+#[macro_export]
+macro_rules! get_tuple {
+    ($ini:expr, $section:expr, $key:expr, $($t:ty),+ $(,)?) => {
+        $ini.get_vec::<String>($section, $key).and_then(|elements| {
+            let mut parts = elements.into_iter();
+            let tuple = ($(parts.next()?.parse::<$t>().ok()?,)+);
+            match parts.next() {
+                Some(_) => None,
+                None => Some(tuple),
+            }
+        })
+    };
+}
+
+#[cfg(test)]
 mod library_test {
     use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// Path in the temporary directory, unique to this test process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tini_{}_{}", process::id(), name))
+    }
+
+    #[test]
+    fn bool() {
+        let ini = Ini::from_buffer("[string]\nabc = true");
+        let abc: Option<bool> = ini.get("string", "abc");
+        assert_eq!(abc, Some(true));
+    }
+
+    #[test]
+    fn get_verbose() {
+        let ini = Ini::from_string("[a]\nport = abc\nx = 1");
+        assert_eq!(ini.get_verbose::<u16>("a", "port"), (None, Some("abc")));
+        assert_eq!(ini.get_verbose::<u16>("a", "x"), (Some(1), Some("1")));
+        assert_eq!(ini.get_verbose::<u16>("a", "y"), (None, None));
+    }
+
+    #[test]
+    fn get_fallback() {
+        let ini = Ini::from_string("[default]\nx = 1\ny = 2\n[dev]\ny = two\nz = 3");
+        let x: Option<u8> = ini.get_fallback(&["dev", "default"], "x");
+        let y: Option<u8> = ini.get_fallback(&["dev", "default"], "y");
+        let z: Option<u8> = ini.get_fallback(&["missing", "dev", "default"], "z");
+        assert_eq!(x, Some(1));
+        assert_eq!(y, Some(2));
+        assert_eq!(z, Some(3));
+        assert_eq!(ini.get_fallback::<u8>(&["dev", "default"], "w"), None);
+    }
+
+    #[test]
+    fn float() {
+        let ini = Ini::from_string("[section]\nname=10.5");
+        let name: Option<f64> = ini.get("section", "name");
+        assert_eq!(name, Some(10.5));
+    }
+
+    #[test]
+    fn float_vec() {
+        let ini = Ini::from_string("[section]\nname=1.2, 3.4, 5.6");
+        let name: Option<Vec<f64>> = ini.get_vec("section", "name");
+        assert_eq!(name, Some(vec![1.2, 3.4, 5.6]));
+    }
+
+    #[test]
+    fn bad_cast() {
+        let ini = Ini::new().section("one").item("a", "3.14");
+        let a: Option<u32> = ini.get("one", "a");
+        assert_eq!(a, None);
+    }
+
+    #[test]
+    fn string_vec() {
+        let ini = Ini::from_string("[section]\nname=a, b, c");
+        let name: Vec<String> = ini.get_vec("section", "name").unwrap_or(vec![]);
+        assert_eq!(name, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn get_vec_skip_empty() {
+        let ini =
+            Ini::from_string("[section]\ninner = 1,,3\ntrailing = 1, 2, \nempty = \nbad = 1,,x");
+        let inner: Option<Vec<i32>> = ini.get_vec_skip_empty("section", "inner");
+        let trailing: Option<Vec<i32>> = ini.get_vec_skip_empty("section", "trailing");
+        let empty: Option<Vec<i32>> = ini.get_vec_skip_empty("section", "empty");
+        let bad: Option<Vec<i32>> = ini.get_vec_skip_empty("section", "bad");
+        assert_eq!(inner, Some(vec![1, 3]));
+        assert_eq!(trailing, Some(vec![1, 2]));
+        assert_eq!(empty, Some(vec![]));
+        assert_eq!(bad, None);
+        assert_eq!(ini.get_vec::<i32>("section", "inner"), None);
+    }
+
+    #[test]
+    fn get_bytes() {
+        let ini = Ini::from_string(
+            "[a]\nlist = DE, ad, B, EF\nhex = DEad0bEF\nodd = abc\nbad = 1, 2G\nlong = 100, 1\nsign = +1",
+        );
+        let expected = Some(vec![0xde, 0xad, 0x0b, 0xef]);
+        assert_eq!(ini.get_bytes("a", "list"), expected);
+        assert_eq!(ini.get_bytes("a", "hex"), expected);
+        assert_eq!(ini.get_bytes("a", "odd"), None);
+        assert_eq!(ini.get_bytes("a", "bad"), None);
+        assert_eq!(ini.get_bytes("a", "long"), None);
+        assert_eq!(ini.get_bytes("a", "sign"), None);
+        assert_eq!(ini.get_bytes("a", "missing"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "smallvec")]
+    fn get_smallvec() {
+        let ini = Ini::from_string("[section]\nrgb = 1, 2, 3\nlong = 1, 2, 3, 4\nbad = 1, x");
+        let rgb = ini.get_smallvec::<u8, 3>("section", "rgb").unwrap();
+        assert!(!rgb.spilled());
+        assert_eq!(rgb.as_slice(), [1, 2, 3]);
+        let long = ini.get_smallvec::<u8, 3>("section", "long").unwrap();
+        assert!(long.spilled());
+        assert_eq!(long.as_slice(), [1, 2, 3, 4]);
+        assert!(ini.get_smallvec::<u8, 3>("section", "bad").is_none());
+    }
+
+    #[test]
+    fn get_range() {
+        let ini = Ini::from_string(
+            "[a]\nup = 8000-8003\ndown = 3 - 1\nsingle = 42\nneg = -2--4\nbad = 1-x\nempty = ",
+        );
+        assert_eq!(ini.get_range("a", "up"), Some(vec![8000, 8001, 8002, 8003]));
+        assert_eq!(ini.get_range("a", "down"), Some(vec![3, 2, 1]));
+        assert_eq!(ini.get_range("a", "single"), Some(vec![42]));
+        assert_eq!(ini.get_range("a", "neg"), Some(vec![-2, -3, -4]));
+        assert_eq!(ini.get_range("a", "bad"), None);
+        assert_eq!(ini.get_range("a", "empty"), None);
+        assert_eq!(ini.get_range("a", "missing"), None);
+
+        let ini = Ini::from_string(
+            "[a]\nmax = 0-65535\nover = 0-65536\nhuge = 0-9223372036854775807\nwide = 9223372036854775807--9223372036854775808",
+        );
+        assert_eq!(ini.get_range("a", "max").map(|x| x.len()), Some(65536));
+        assert_eq!(ini.get_range("a", "over"), None);
+        assert_eq!(ini.get_range("a", "huge"), None);
+        assert_eq!(ini.get_range("a", "wide"), None);
+    }
+
+    #[test]
+    fn get_indexed_vec() {
+        let ini = Ini::from_string(
+            "[a]\nitem.2 = 30\nitem.0 = 10\nitem.1 = 20\nitem.x = 0\nitem = 0\nitems.3 = 0\n\
+             [b]\nitem.5 = 3\nitem.0 = 1\nitem.10 = 4\nitem.+2 = 0\n[c]\nitem.0 = x",
+        );
+        let contiguous: Vec<u8> = ini.get_indexed_vec("a", "item").unwrap();
+        assert_eq!(contiguous, [10, 20, 30]);
+        let gapped: Vec<u8> = ini.get_indexed_vec("b", "item").unwrap();
+        assert_eq!(gapped, [1, 3, 4]);
+        assert_eq!(ini.get_indexed_vec::<u8>("c", "item"), None);
+        assert_eq!(ini.get_indexed_vec::<u8>("a", "missing"), None);
+        assert_eq!(ini.get_indexed_vec::<u8>("d", "item"), None);
+    }
+
+    #[test]
+    fn get_addr() {
+        let ini = Ini::from_string(
+            "[a]\nv4 = 10.0.0.1\nv6 = fe80::1\nv4port = 10.0.0.1:80\nv6port = [fe80::1]:8080\nhost = localhost:80",
+        );
+        let v4 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let v6 = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+        assert_eq!(ini.get_ip_addr("a", "v4"), Some(v4));
+        assert_eq!(ini.get_ip_addr("a", "v6"), Some(v6));
+        assert_eq!(ini.get_ip_addr("a", "v4port"), None);
+        assert_eq!(
+            ini.get_socket_addr("a", "v4port"),
+            Some(SocketAddr::new(v4, 80))
+        );
+        assert_eq!(
+            ini.get_socket_addr("a", "v6port"),
+            Some(SocketAddr::new(v6, 8080))
+        );
+        assert_eq!(ini.get_socket_addr("a", "v4"), None);
+        assert_eq!(ini.get_socket_addr("a", "host"), None);
+        assert_eq!(ini.total_keys(), 5);
+    }
+
+    #[test]
+    fn get_enumerated() {
+        let ini = Ini::from_string("[a]\nlist = 1,2,3\nbad = 1, x");
+        let list: Vec<(usize, u8)> = ini.get_enumerated("a", "list").unwrap();
+        assert_eq!(list, [(0, 1), (1, 2), (2, 3)]);
+        let odd: Vec<u8> = list
+            .into_iter()
+            .filter(|(i, _)| i % 2 == 0)
+            .map(|(_, x)| x)
+            .collect();
+        assert_eq!(odd, [1, 3]);
+        assert_eq!(ini.get_enumerated::<u8>("a", "bad"), None);
+    }
+
+    #[test]
+    fn get_int_radix() {
+        let ini = Ini::from_string(
+            "[a]\nhex = 0xff\nHEX = 0XfF\noct = 0o777\nbin = 0b1010\ndec = 42\nneg = -0x10\nplus = +0b1\n\
+             bad = 0x\nsign = 0x-1\nwide = 0x100\ndigit = 0o8",
+        );
+        assert_eq!(ini.get_int_radix::<u8>("a", "hex"), Some(255));
+        assert_eq!(ini.get_int_radix::<u8>("a", "HEX"), Some(255));
+        assert_eq!(ini.get_int_radix::<u16>("a", "oct"), Some(511));
+        assert_eq!(ini.get_int_radix::<i32>("a", "bin"), Some(10));
+        assert_eq!(ini.get_int_radix::<i32>("a", "dec"), Some(42));
+        assert_eq!(ini.get_int_radix::<i32>("a", "neg"), Some(-16));
+        assert_eq!(ini.get_int_radix::<u8>("a", "plus"), Some(1));
+        assert_eq!(ini.get_int_radix::<u8>("a", "neg"), None);
+        assert_eq!(ini.get_int_radix::<u8>("a", "bad"), None);
+        assert_eq!(ini.get_int_radix::<i8>("a", "sign"), None);
+        assert_eq!(ini.get_int_radix::<u8>("a", "wide"), None);
+        assert_eq!(ini.get_int_radix::<u8>("a", "digit"), None);
+        assert_eq!(ini.get::<u8>("a", "hex"), None);
+    }
+
+    #[test]
+    fn get_interpolated() {
+        let config = Ini::from_string(
+            "[a]\nbase = /opt\npath = %(base)s/data\nother = ${b:x}-${base}\nliteral = 100%% $$5 % $\n\
+             missing = %(nope)s\nloop = %(loop2)s\nloop2 = ${a:loop}\nport = %(num)s\nnum = 80\n[b]\nx = %(y)s\ny = 1",
+        );
+        let path: Option<String> = config.get_interpolated("a", "path").unwrap();
+        assert_eq!(path, Some("/opt/data".to_owned()));
+        let other: Option<String> = config.get_interpolated("a", "other").unwrap();
+        assert_eq!(other, Some("1-/opt".to_owned()));
+        let literal: Option<String> = config.get_interpolated("a", "literal").unwrap();
+        assert_eq!(literal, Some("100% $5 % $".to_owned()));
+        let port: Option<u16> = config.get_interpolated("a", "port").unwrap();
+        assert_eq!(port, Some(80));
+        assert_eq!(config.get_interpolated::<String>("a", "none"), Ok(None));
+        assert_eq!(
+            config.get_interpolated::<String>("a", "missing"),
+            Err(InterpolationError::Missing(
+                "a".to_owned(),
+                "nope".to_owned()
+            ))
+        );
+        assert_eq!(
+            config.get_interpolated::<String>("a", "loop"),
+            Err(InterpolationError::Cycle("a".to_owned(), "loop".to_owned()))
+        );
+
+        let raw: Option<String> = config.get("a", "path");
+        assert_eq!(raw, Some("%(base)s/data".to_owned()));
+
+        let mut builder = Ini::builder();
+        builder.section("a").item("k0", &"x".repeat(1 << 10));
+        for i in 1..64 {
+            builder.item(format!("k{}", i), format!("${{k{}}}${{k{}}}", i - 1, i - 1));
+        }
+        let config = builder.build();
+        let k10: Option<String> = config.get_interpolated("a", "k10").unwrap();
+        assert_eq!(k10.map(|x| x.len()), Some(1 << 20));
+        assert_eq!(
+            config.get_interpolated::<String>("a", "k63"),
+            Err(InterpolationError::TooLong(
+                "a".to_owned(),
+                "k11".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_vec() {
+        let ini = Ini::from_string("[section]\nlist = 1, two, 3, -4");
+        let list = ini.parse_vec::<u8>("section", "list").unwrap();
+        assert_eq!(list.len(), 4);
+        assert_eq!(list[0], Ok(1));
+        assert!(list[1].is_err());
+        assert_eq!(list[2], Ok(3));
+        assert!(list[3].is_err());
+        assert!(ini.parse_vec::<u8>("section", "missing").is_none());
+    }
+
+    #[test]
+    fn parse_error() {
+        let ini = Ini::from_string("[section]\nlist = 1, 2, --, 4");
+        let name: Option<Vec<u8>> = ini.get_vec("section", "list");
+        assert_eq!(name, None);
+    }
+
+    #[test]
+    fn get_or_macro() {
+        let ini = Ini::from_string("[section]\nlist = 1, 2, --, 4");
+        let with_value: Vec<u8> = ini.get_vec("section", "list").unwrap_or(vec![1, 2, 3, 4]);
+        assert_eq!(with_value, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn ordering_iter() {
+        let ini = Ini::from_string("[a]\nc = 1\nb = 2\na = 3");
+        let keys: Vec<&String> = ini.data.get("a").unwrap().iter().map(|(k, _)| k).collect();
+        assert_eq!(["c", "b", "a"], keys[..]);
+    }
+
+    #[test]
+    fn ordering_keys() {
+        let ini = Ini::from_string("[a]\nc = 1\nb = 2\na = 3");
+        let keys: Vec<&String> = ini.data.get("a").unwrap().keys().collect();
+        assert_eq!(["c", "b", "a"], keys[..]);
+    }
+
+    #[test]
+    fn mutating() {
+        let mut config = Ini::new()
+            .section("items")
+            .item("a", "1")
+            .item("b", "2")
+            .item("c", "3");
+
+        // mutate items
+        for (_, item) in config.iter_mut() {
+            for (_, value) in item {
+                let v: i32 = value.parse().unwrap();
+                *value = format!("{}", v + 1);
+            }
+        }
+
+        let a_val: Option<u8> = config.get("items", "a");
+        let b_val: Option<u8> = config.get("items", "b");
+        let c_val: Option<u8> = config.get("items", "c");
+
+        assert_eq!(a_val, Some(2));
+        assert_eq!(b_val, Some(3));
+        assert_eq!(c_val, Some(4));
+    }
+
+    #[test]
+    fn redefine_item() {
+        let config = Ini::new()
+            .section("items")
+            .item("one", "3")
+            .item("two", "2")
+            .item("one", "1");
+
+        let one: Option<i32> = config.get("items", "one");
+        assert_eq!(one, Some(1));
+    }
+
+    #[test]
+    fn insert() {
+        let mut config = Ini::new().section("a").item("x", "1");
+        config.insert("b", "y", "2");
+        config.insert("a", "x".to_owned(), 3.to_string());
+        assert_eq!(config.to_buffer(), "[a]\nx = 3\n\n[b]\ny = 2");
+    }
+
+    #[test]
+    fn current_section() {
+        let config = Ini::new();
+        assert_eq!(config.current_section(), "");
+        let config = config.section("a").item("x", "1");
+        assert_eq!(config.current_section(), "a");
+        let config = config.section("b");
+        assert_eq!(config.current_section(), "b");
+        let config = Ini::from_string("[a]\nx = 1\n[c]\ny = 2");
+        assert_eq!(config.current_section(), "c");
+    }
+
+    #[test]
+    fn redefine_section() {
+        let config = Ini::new()
+            .section("one")
+            .item("a", "1")
+            .section("two")
+            .item("b", "2")
+            .section("one")
+            .item("c", "3");
+
+        let a_val: Option<i32> = config.get("one", "a");
+        let c_val: Option<i32> = config.get("one", "c");
+
+        assert_eq!(a_val, Some(1));
+        assert_eq!(c_val, Some(3));
+    }
+
+    #[test]
+    fn with_escaped_items() {
+        let config = Ini::new()
+            .section("default")
+            .item("vector", r"1, 2, 3, 4, 5, 6, 7");
+
+        let vector: Vec<String> = config.get_vec("default", "vector").unwrap();
+        assert_eq!(vector, ["1", "2", "3", "4", "5", "6", "7"]);
+    }
+
+    #[test]
+    fn use_item_vec() {
+        let config =
+            Ini::new()
+                .section("default")
+                .item_vec_with_sep("a", &["a,b", "c,d", "e"], "|");
+
+        let v: Vec<String> = config.get_vec_with_sep("default", "a", "|").unwrap();
+        assert_eq!(v, [r"a,b", "c,d", "e"]);
+    }
+
+    #[test]
+    fn quoted_string_vec() {
+        let ini = Ini::from_string(
+            r#"[section]
+names = "Alice", "Bob", "Carol", """#,
+        );
+        let names: Vec<String> = ini.get_vec_trimmed("section", "names", &['"']).unwrap();
+        assert_eq!(names, ["Alice", "Bob", "Carol", ""]);
+
+        let ini = Ini::from_string(
+            r#"[section]
+names = "Smith\, John", "Doe""#,
+        )
+        .section("section")
+        .item_vec_with_sep("piped", &["'a|b'", "'c'"], "|");
+        let names: Vec<String> = ini.get_vec_trimmed("section", "names", &['"']).unwrap();
+        assert_eq!(names, ["Smith, John", "Doe"]);
+        let piped: Vec<String> = ini.get_vec_trimmed("section", "piped", &['\'']).unwrap();
+        assert_eq!(piped, ["a|b", "c"]);
+    }
+
+    #[test]
+    fn csv_string_vec() {
+        let ini = Ini::from_string(
+            r#"[section]
+names = "Smith, John", "Doe, ""JD"" Jane", Roe
+numbers = "1", 2, " 3"
+broken = "Smith, John"#,
+        );
+        let names: Vec<String> = ini.get_vec_csv("section", "names").unwrap();
+        assert_eq!(names, ["Smith, John", r#"Doe, "JD" Jane"#, "Roe"]);
+        let numbers: Option<Vec<u8>> = ini.get_vec_csv("section", "numbers");
+        assert_eq!(numbers, None);
+        let broken: Option<Vec<String>> = ini.get_vec_csv("section", "broken");
+        assert_eq!(broken, None);
+    }
+
+    #[test]
+    fn csv_empty_strings() {
+        let ini =
+            Ini::from_string("[section]\nempty = \"\", \"a\", \"\"\nonly = \"\"\nbare = , a,");
+        let empty: Vec<String> = ini.get_vec_csv("section", "empty").unwrap();
+        assert_eq!(empty, ["", "a", ""]);
+        let only: Vec<String> = ini.get_vec_csv("section", "only").unwrap();
+        assert_eq!(only, [""]);
+        let bare: Vec<String> = ini.get_vec_csv("section", "bare").unwrap();
+        assert_eq!(bare, ["", "a", ""]);
+    }
+
+    #[test]
+    fn builder() {
+        let mut builder = Ini::builder();
+        builder.section("a").item("x", "1");
+        for i in 0..3 {
+            if i % 2 == 0 {
+                builder.item(format!("k{}", i), i.to_string());
+            }
+        }
+        builder.section("b").item("y", "2");
+        let config = builder.build();
+
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 1\nk0 = 0\nk2 = 2\n\n[b]\ny = 2"
+        );
+    }
+
+    #[test]
+    fn reload_from_file() {
+        let path = temp_path("reload_from_file.ini");
+        fs::write(&path, "[a]\nx = 1").unwrap();
+
+        let mut config = Ini::from_file(&path).unwrap();
+        for (_, item) in config.iter_mut() {
+            for (_, value) in item {
+                *value = String::from("changed");
+            }
+        }
+        config = config.section("b").item("y", "2");
+
+        config.reload_from_file(&path).unwrap();
+
+        assert_eq!(config.to_buffer(), "[a]\nx = 1");
+
+        let mut config = Ini::new()
+            .line_ending(LineEnding::Crlf)
+            .escape_values(true)
+            .section_header(|name| format!("[ {} ]", name));
+        fs::write(&path, "[a]\nx = t\ty\n[b]\ny = 2").unwrap();
+        config.reload_from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            config.to_buffer(),
+            "[ a ]\r\nx = t\\x09y\r\n\r\n[ b ]\r\ny = 2"
+        );
+    }
+
+    #[test]
+    fn append_section_to_file() {
+        let path = temp_path("append_section_to_file.ini");
+        let _ = fs::remove_file(&path);
+
+        let config = Ini::new()
+            .section("a")
+            .item("x", "1")
+            .section("b")
+            .item("y", "2");
+        config.append_section_to_file(&path, "b").unwrap();
+        config.append_section_to_file(&path, "a").unwrap();
+        let missing = config.append_section_to_file(&path, "c");
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(content, "[b]\ny = 2\n\n[a]\nx = 1");
+
+        let config = config.line_ending(LineEnding::Crlf);
+        config.append_section_to_file(&path, "b").unwrap();
+        config.append_section_to_file(&path, "a").unwrap();
+        let crlf = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(crlf, "[b]\r\ny = 2\r\n\r\n[a]\r\nx = 1");
+        let config = Ini::from_string(&content);
+        assert_eq!(config.get::<u8>("a", "x"), Some(1));
+        assert_eq!(config.get::<u8>("b", "y"), Some(2));
+    }
+
+    #[test]
+    fn to_file_atomic() {
+        let path = temp_path("to_file_atomic.ini");
+        fs::write(&path, "[old]\nx = 0").unwrap();
+
+        let config = Ini::new().section("a").item("x", "1");
+        config.to_file_atomic(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let prefix = format!("{}.", path.file_name().unwrap().to_string_lossy());
+        let leftovers = fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .count();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(content, "[a]\nx = 1");
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn to_file_with_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_path("to_file_with_mode.ini");
+        let config = Ini::new().section("a").item("secret", "1");
+        config.to_file_with_mode(&path, 0o600).unwrap();
+        let new_mode = fs::metadata(&path).unwrap().permissions().mode();
+        config.to_file_with_mode(&path, 0o640).unwrap();
+        let existing_mode = fs::metadata(&path).unwrap().permissions().mode();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(new_mode & 0o777, 0o600);
+        assert_eq!(existing_mode & 0o777, 0o640);
+        assert_eq!(content, "[a]\nsecret = 1");
+    }
+
+    #[test]
+    fn section_pairs() {
+        let config = Ini::from_string("[a]\nz = 1\ny = 2\nx = 3");
+        let pairs = config.section_pairs("a").unwrap();
+        let expected: Vec<(String, String)> = vec![
+            ("z".into(), "1".into()),
+            ("y".into(), "2".into()),
+            ("x".into(), "3".into()),
+        ];
+        assert_eq!(pairs, expected);
+        assert_eq!(config.section_pairs("b"), None);
+    }
+
+    #[test]
+    fn require_section_header() {
+        let options = ParseOptions::new().require_section_header(true);
+        let config = Ini::from_string_with_options("; comment\n[a]\nx = 1", &options).unwrap();
+        let x: Option<i32> = config.get("a", "x");
+        assert_eq!(x, Some(1));
+
+        let err = Ini::from_string_with_options("; comment\nx = 1\n[a]\ny = 2", &options);
+        assert_eq!(err.unwrap_err().line(), 2);
+
+        let config = Ini::from_string_with_options("x = 1\n[a]", &ParseOptions::new()).unwrap();
+        let x: Option<i32> = config.get("", "x");
+        assert_eq!(x, Some(1));
+    }
+
+    #[test]
+    fn treat_blank_as_missing() {
+        let buffer = "[a]\nx =    \ny = 1\nz =";
+        let options = ParseOptions::new().treat_blank_as_missing(true);
+        let config = Ini::from_string_with_options(buffer, &options).unwrap();
+        assert_eq!(config.get::<String>("a", "x"), None);
+        assert_eq!(config.get::<String>("a", "z"), None);
+        assert_eq!(config.to_buffer(), "[a]\ny = 1");
+
+        let config = Ini::from_string_with_options(buffer, &ParseOptions::new()).unwrap();
+        assert_eq!(config.get::<String>("a", "x"), Some(String::new()));
+        assert_eq!(config.total_keys(), 3);
+    }
+
+    #[test]
+    fn map_values() {
+        let mut config = Ini::from_string("[a]\nz = one\ny = two\n[b]\nx = three");
+        config.map_values(|_, _, value| value.to_uppercase());
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nz = ONE\ny = TWO\n\n[b]\nx = THREE"
+        );
+    }
+
+    #[test]
+    fn recorded_vec_format() {
+        let config = Ini::new()
+            .section("default")
+            .item_vec_with_sep("a", &[1, 2, 3], "|")
+            .item_vec_with_sep("b", &["x", "y"], " / ")
+            .item_vec("c", &[4, 5]);
+
+        let a: Vec<u8> = config.get_vec("default", "a").unwrap();
+        let b: Vec<String> = config.get_vec("default", "b").unwrap();
+        let c: Vec<u8> = config.get_vec("default", "c").unwrap();
+        assert_eq!(a, [1, 2, 3]);
+        assert_eq!(b, ["x", "y"]);
+        assert_eq!(c, [4, 5]);
+
+        // plain item replaces the list and its format
+        let config = config.item("a", "1,2");
+        assert_eq!(config.vec_format("default", "a"), None);
+        let a: Vec<u8> = config.get_vec("default", "a").unwrap();
+        assert_eq!(a, [1, 2]);
+
+        // formats are dropped when values change by other means
+        let mut config = Ini::new()
+            .section("default")
+            .item_vec_with_sep("a", &[1, 2], "|")
+            .item_vec_with_sep("b", &[3, 4], "|");
+        config.map_values(|_, key, value| match key {
+            "a" => "1,2".to_owned(),
+            _ => value.to_owned(),
+        });
+        assert_eq!(config.vec_format("default", "a"), None);
+        assert!(config.vec_format("default", "b").is_some());
+        let a: Vec<u8> = config.get_vec("default", "a").unwrap();
+        assert_eq!(a, [1, 2]);
+
+        config.merge(&Ini::from_string("[default]\nb = 5,6"));
+        let b: Vec<u8> = config.get_vec("default", "b").unwrap();
+        assert_eq!(b, [5, 6]);
+
+        let mut config = Ini::new()
+            .section("default")
+            .item_vec_with_sep("a", &[1, 2], "|");
+        for (_, section) in config.sections_mut() {
+            section.insert("a".to_owned(), "3,4".to_owned());
+        }
+        let a: Vec<u8> = config.get_vec("default", "a").unwrap();
+        assert_eq!(a, [3, 4]);
+    }
+
+    #[test]
+    fn section_trailing_text() {
+        let options = ParseOptions::new();
+        let config = Ini::from_string_with_options("[a]   \nx = 1", &options).unwrap();
+        assert_eq!(config.to_buffer(), "[a]\nx = 1");
+        let err = Ini::from_string_with_options("[a]\nx = 1\n[b] junk", &options).unwrap_err();
+        assert_eq!(err.line(), 3);
+        assert_eq!(err.message(), "unexpected text after section header: junk");
+    }
+
+    #[test]
+    fn try_from_file() {
+        let missing = temp_path("try_from_file_missing.ini");
+        assert!(matches!(Ini::try_from_file(&missing), Err(IniError::Io(_))));
+
+        let path = temp_path("try_from_file_malformed.ini");
+        fs::write(&path, "[a]\nx = 1\n[b\ny = 2").unwrap();
+        let result = Ini::try_from_file(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(IniError::Parse(err)) => assert_eq!(err.line(), 3),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tuple() {
+        let config = Ini::from_string("[a]\npoint = 3, 4.5, hello\nshort = 1, 2");
+        let point = get_tuple!(config, "a", "point", i32, f64, String);
+        assert_eq!(point, Some((3, 4.5, String::from("hello"))));
+
+        let short = get_tuple!(config, "a", "short", i32, i32, i32);
+        assert_eq!(short, None);
+        let long = get_tuple!(config, "a", "point", i32, f64);
+        assert_eq!(long, None);
+        let bad = get_tuple!(config, "a", "point", i32, i32, String);
+        assert_eq!(bad, None);
+
+        let config = Ini::from_string("[a]\nname = Smith\\, John, 42")
+            .section("a")
+            .item_vec_with_sep("piped", &["x", "1"], "|");
+        let name = get_tuple!(config, "a", "name", String, u8);
+        assert_eq!(name, Some((String::from("Smith, John"), 42)));
+        let piped = get_tuple!(config, "a", "piped", String, u8);
+        assert_eq!(piped, Some((String::from("x"), 1)));
+    }
+
+    #[test]
+    fn comments() {
+        let mut config = Ini::from_string("; header\n\n[a]\n; about x\nx = 1\n;\n; about y\ny = 2");
+        assert_eq!(config.comment_for("a", "x"), Some("about x"));
+        assert_eq!(config.comment_for("a", "y"), Some("about y"));
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\n; about x\nx = 1\n; about y\ny = 2"
+        );
+        let spaced = Ini::from_string("[a]\n;\n; one\n;\n; two\nx = 1");
+        assert_eq!(spaced.comment_for("a", "x"), Some("one\ntwo"));
+
+        config.set_comment("a", "y", "new comment");
+        config.set_comment("a", "missing", "ignored");
+        assert_eq!(config.comment_for("a", "y"), Some("new comment"));
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\n; about x\nx = 1\n; new comment\ny = 2"
+        );
+    }
+
+    #[test]
+    fn section_comments() {
+        let buffer =
+            "; first\n; section\n[a]\n; key\nx = 1\n\n; second\n\n[b]\ny = 2\n; again\n[a]\nz = 3";
+        let mut config = Ini::from_string(buffer);
+        assert_eq!(config.section_comment("a"), Some("first\nsection"));
+        assert_eq!(config.section_comment("b"), None);
+        assert_eq!(config.comment_for("a", "x"), Some("key"));
+
+        let expected = "; first\n; section\n[a]\n; key\nx = 1\nz = 3\n\n[b]\ny = 2";
+        assert_eq!(config.to_buffer(), expected);
+        assert_eq!(config.serialized_len(), expected.len());
+        assert_eq!(Ini::from_string(expected).to_buffer(), expected);
+
+        config.set_section_comment("b", "other");
+        config.prefix_sections("p.");
+        assert_eq!(config.section_comment("p.b"), Some("other"));
+        let extracted = config.extract_section("p.a").unwrap();
+        assert_eq!(extracted.section_comment("p.a"), Some("first\nsection"));
+    }
+
+    #[test]
+    fn inline_comments() {
+        let buffer = "[a]\n; above\nx = 1 ; inline\ny = 2\n\n[b]\nz = 3 ; another";
+        let options = ParseOptions::new().inline_comments(true);
+        let config = Ini::from_string_with_options(buffer, &options).unwrap();
+        assert_eq!(config.inline_comment_for("a", "x"), Some("inline"));
+        assert_eq!(config.inline_comment_for("a", "y"), None);
+        assert_eq!(config.to_buffer(), buffer);
+
+        let config = Ini::from_string_with_options(buffer, &ParseOptions::new()).unwrap();
+        assert_eq!(config.inline_comment_for("a", "x"), None);
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\n; above\nx = 1\ny = 2\n\n[b]\nz = 3"
+        );
+    }
+
+    #[test]
+    fn quoted_whitespace() {
+        let config = Ini::new()
+            .section("a")
+            .item("k", "  x  ")
+            .item("blank", "   ")
+            .item("plain", "x y")
+            .item("quotes", "\"x\"")
+            .item("padded", "\" x \"");
+        let buffer = config.to_buffer();
+        assert_eq!(
+            buffer,
+            "[a]\nk = \"  x  \"\nblank = \"   \"\nplain = x y\nquotes = \"x\"\npadded = \"\"\" x \"\"\""
+        );
+        assert_eq!(config.serialized_len(), buffer.len());
+
+        let config = Ini::from_string(&buffer);
+        let k: String = config.get("a", "k").unwrap();
+        assert_eq!(k, "  x  ");
+        let blank: String = config.get("a", "blank").unwrap();
+        assert_eq!(blank, "   ");
+        let quotes: String = config.get("a", "quotes").unwrap();
+        assert_eq!(quotes, "\"x\"");
+        let padded: String = config.get("a", "padded").unwrap();
+        assert_eq!(padded, "\" x \"");
+        assert!(config.check_roundtrip());
+    }
+
+    #[test]
+    fn keys_matching() {
+        let config = Ini::from_string("[a]\nx.1 = 1\ny = 2\nx.2 = 3\nxx = 4");
+        assert_eq!(config.keys_matching("a", "x."), ["x.1", "x.2"]);
+        assert_eq!(config.keys_matching("a", "x*"), ["x.1", "x.2", "xx"]);
+        assert_eq!(config.keys_matching("a", "*").len(), 4);
+        assert!(config.keys_matching("a", "z").is_empty());
+        assert!(config.keys_matching("b", "x").is_empty());
+    }
+
+    #[test]
+    fn to_toml_string() {
+        let config = Ini::from_string(
+            "[a b]\nname = x \"y\"\nint = -10\nfloat = 1.5e3\nzero = 007\nbool = false\nk.1 = 1.\n[c.d]\nv = 1e+2",
+        );
+        assert_eq!(
+            config.to_toml_string(),
+            "[\"a b\"]\nname = \"x \\\"y\\\"\"\nint = -10\nfloat = 1.5e3\nzero = \"007\"\nbool = false\n\"k.1\" = \"1.\"\n\n[c.d]\nv = 1e+2"
+        );
+        assert_eq!(Ini::new().to_toml_string(), "");
+    }
+
+    #[test]
+    fn get_vec_exact() {
+        let config = Ini::from_string("[a]\nlist = 1, 2, 3\nbad = 1, x, 3");
+        let exact: Option<Vec<u8>> = config.get_vec_exact("a", "list", 3);
+        assert_eq!(exact, Some(vec![1, 2, 3]));
+        let short: Option<Vec<u8>> = config.get_vec_exact("a", "list", 4);
+        assert_eq!(short, None);
+        let long: Option<Vec<u8>> = config.get_vec_exact("a", "list", 2);
+        assert_eq!(long, None);
+        let bad: Option<Vec<u8>> = config.get_vec_exact("a", "bad", 3);
+        assert_eq!(bad, None);
+    }
+
+    #[test]
+    fn get_vec_or_default() {
+        let config = Ini::from_string("[a]\nlist = 1, -2, 3,, 4");
+        let value: Option<Vec<u8>> = config.get_vec_or_default("a", "list", 9);
+        assert_eq!(value, Some(vec![1, 9, 3, 9, 4]));
+        let missing: Option<Vec<u8>> = config.get_vec_or_default("a", "none", 9);
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn merge_section() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\ny = 2");
+        config.mark_clean();
+        let other = Ini::from_string("; new\n[b]\ny = 3\nz = 4\n[a]\nx = 5\n[c]\nw = 6");
+
+        config.merge_section(&other, "d");
+        assert!(!config.is_modified());
+
+        config.merge_section(&other, "b");
+        assert!(config.is_modified());
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\n\n; new\n[b]\ny = 3\nz = 4");
+
+        config.merge_section(&other, "c");
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 1\n\n; new\n[b]\ny = 3\nz = 4\n\n[c]\nw = 6"
+        );
+    }
 
     #[test]
-    fn bool() {
-        let ini = Ini::from_buffer("[string]\nabc = true");
-        let abc: Option<bool> = ini.get("string", "abc");
-        assert_eq!(abc, Some(true));
+    fn get_or_default() {
+        let config = Ini::from_string("[a]\nx = 5\nflag = yes");
+        assert_eq!(config.get_or_default::<i32>("a", "x"), 5);
+        assert_eq!(config.get_or_default::<i32>("a", "missing"), 0);
+        assert!(!config.get_or_default::<bool>("a", "missing"));
+        assert!(!config.get_or_default::<bool>("a", "flag"));
+        assert_eq!(config.get_or_default::<String>("b", "x"), "");
     }
 
     #[test]
-    fn float() {
-        let ini = Ini::from_string("[section]\nname=10.5");
-        let name: Option<f64> = ini.get("section", "name");
-        assert_eq!(name, Some(10.5));
+    fn require_section() {
+        fn load(config: &Ini) -> Result<Vec<String>, Box<dyn error::Error>> {
+            Ok(config
+                .require_section("a")?
+                .map(|(k, _)| k.clone())
+                .collect())
+        }
+        let config = Ini::from_string("[a]\nx = 1\ny = 2\n[b]\nz = 3");
+        assert_eq!(load(&config).unwrap(), ["x", "y"]);
+        assert_eq!(config.require_section("b").unwrap().count(), 1);
+        assert_eq!(
+            config.require_section("c").err(),
+            Some(MissingSection("c".to_owned()))
+        );
+        let err = load(&Ini::from_string("[b]\nz = 3")).unwrap_err();
+        assert_eq!(err.to_string(), "missing required section a");
     }
 
     #[test]
-    fn float_vec() {
-        let ini = Ini::from_string("[section]\nname=1.2, 3.4, 5.6");
-        let name: Option<Vec<f64>> = ini.get_vec("section", "name");
-        assert_eq!(name, Some(vec![1.2, 3.4, 5.6]));
+    fn custom_separator() {
+        let options = ParseOptions::new().separator("==");
+        let config = Ini::from_string_with_options("[a]\nkey == value\nx==1", &options).unwrap();
+        assert_eq!(config.get::<String>("a", "key"), Some("value".to_owned()));
+        assert_eq!(config.get::<u8>("a", "x"), Some(1));
     }
 
     #[test]
-    fn bad_cast() {
-        let ini = Ini::new().section("one").item("a", "3.14");
-        let a: Option<u32> = ini.get("one", "a");
-        assert_eq!(a, None);
+    fn get_vec_ws() {
+        let config = Ini::from_string("[a]\nflags = -O2   -g -Wall\nnums = 1 2\tx\nempty =");
+        let flags: Vec<String> = config.get_vec_ws("a", "flags").unwrap();
+        assert_eq!(flags, ["-O2", "-g", "-Wall"]);
+        let nums: Option<Vec<u8>> = config.get_vec_ws("a", "nums");
+        assert_eq!(nums, None);
+        let empty: Vec<String> = config.get_vec_ws("a", "empty").unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(config.get_vec_ws::<String>("a", "missing"), None);
     }
 
     #[test]
-    fn string_vec() {
-        let ini = Ini::from_string("[section]\nname=a, b, c");
-        let name: Vec<String> = ini.get_vec("section", "name").unwrap_or(vec![]);
-        assert_eq!(name, ["a", "b", "c"]);
+    fn extend() {
+        let mut config = Ini::from_string("[a]\nx = 1\ny = 2");
+        config.mark_clean();
+        config.extend(Vec::new());
+        assert!(!config.is_modified());
+
+        let entry = |s: &str, k: &str, v: &str| (s.to_owned(), k.to_owned(), v.to_owned());
+        config.extend(vec![
+            entry("c", "z", "3"),
+            entry("a", "x", "4"),
+            entry("b", "w", "5"),
+            entry("a", "v", "6"),
+        ]);
+        assert!(config.is_modified());
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 4\ny = 2\nv = 6\n\n[c]\nz = 3\n\n[b]\nw = 5"
+        );
     }
 
     #[test]
-    fn parse_error() {
-        let ini = Ini::from_string("[section]\nlist = 1, 2, --, 4");
-        let name: Option<Vec<u8>> = ini.get_vec("section", "list");
-        assert_eq!(name, None);
+    fn check_roundtrip() {
+        let config = Ini::new()
+            .section("a")
+            .item("list", "1, 2")
+            .item("k=v", "x = y")
+            .item("quoted", "\"q\"")
+            .item("padded", "  p ")
+            .section("b")
+            .item("empty", "");
+        assert!(config.check_roundtrip());
+        assert!(Ini::new().check_roundtrip());
+
+        let config = Ini::new().section("a").item("text", "1\n2");
+        assert!(!config.check_roundtrip());
+        assert!(config.escape_values(true).check_roundtrip());
+
+        let config = Ini::new().section("a").item("comment", "x ; y");
+        assert!(!config.check_roundtrip());
+        assert!(config.escape_values(true).check_roundtrip());
     }
 
     #[test]
-    fn get_or_macro() {
-        let ini = Ini::from_string("[section]\nlist = 1, 2, --, 4");
-        let with_value: Vec<u8> = ini.get_vec("section", "list").unwrap_or(vec![1, 2, 3, 4]);
-        assert_eq!(with_value, [1, 2, 3, 4]);
+    fn get_mapped() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum Level {
+            Debug,
+            Info,
+            Warn,
+        }
+        let levels = [
+            ("debug", Level::Debug),
+            ("info", Level::Info),
+            ("warn", Level::Warn),
+            ("WARN", Level::Info),
+        ];
+        let config = Ini::from_string("[a]\nx = debug\ny = Info\nz = WARN\nw = Warn\nv = error");
+        assert_eq!(config.get_mapped("a", "x", &levels), Some(Level::Debug));
+        assert_eq!(config.get_mapped("a", "y", &levels), Some(Level::Info));
+        assert_eq!(config.get_mapped("a", "z", &levels), Some(Level::Info));
+        assert_eq!(config.get_mapped("a", "w", &levels), Some(Level::Warn));
+        assert_eq!(config.get_mapped("a", "v", &levels), None);
+        assert_eq!(config.get_mapped("a", "missing", &levels), None);
     }
 
     #[test]
-    fn ordering_iter() {
-        let ini = Ini::from_string("[a]\nc = 1\nb = 2\na = 3");
-        let keys: Vec<&String> = ini.data.get("a").unwrap().iter().map(|(k, _)| k).collect();
-        assert_eq!(["c", "b", "a"], keys[..]);
+    fn indented_keys() {
+        let options = ParseOptions::new().indented_keys(true);
+        let buffer = "[app]\nname = demo\nserver:\n  host: localhost\n\n  ; port\n\tport = 80\ndb:\n  user: admin\nlevel = 1\n  indented = 2";
+        let config = Ini::from_string_with_options(buffer, &options).unwrap();
+        assert_eq!(
+            config.to_buffer(),
+            "[app]\nname = demo\nserver.host = localhost\n; port\nserver.port = 80\ndb.user = admin\nlevel = 1\nindented = 2"
+        );
+
+        let err = Ini::from_string_with_options("[a]\nx:\n  y:\n    z: 1", &options).unwrap_err();
+        assert_eq!(err.line(), 3);
+        let config = Ini::from_string("[a]\nx = 1\n  y = 2");
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\ny = 2");
     }
 
     #[test]
-    fn ordering_keys() {
-        let ini = Ini::from_string("[a]\nc = 1\nb = 2\na = 3");
-        let keys: Vec<&String> = ini.data.get("a").unwrap().keys().collect();
-        assert_eq!(["c", "b", "a"], keys[..]);
+    fn clone_section_as() {
+        let mut config = Ini::from_string("[a]\n; first\nx = 1\ny = 2\n[b]\nz = 3");
+        config.mark_clean();
+        assert!(!config.clone_section_as("missing", "c"));
+        assert!(!config.clone_section_as("a", "b"));
+        assert!(!config.is_modified());
+
+        assert!(config.clone_section_as("a", "c"));
+        assert!(config.is_modified());
+        config.insert("c", "x", "4");
+        config.insert("a", "y", "5");
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\n; first\nx = 1\ny = 5\n\n[b]\nz = 3\n\n[c]\n; first\nx = 4\ny = 2"
+        );
     }
 
     #[test]
-    fn mutating() {
+    fn item_if() {
+        let config = Ini::new()
+            .section("a")
+            .item_if(true, "x", "1")
+            .item_if(false, "y", "2");
+        assert_eq!(config.get::<u8>("a", "x"), Some(1));
+        assert_eq!(config.get::<u8>("a", "y"), None);
+        assert_eq!(config.to_buffer(), "[a]\nx = 1");
+    }
+
+    #[test]
+    fn get_json_array() {
+        let config = Ini::from_string(
+            "[a]\nnums = [1, 2,3]\nstrs = [\"x\", \"y, z\", \"\"]\nbad = [1, x]\nplain = 1, 2",
+        );
+        let nums: Vec<u8> = config.get_json_array("a", "nums").unwrap();
+        assert_eq!(nums, [1, 2, 3]);
+        let strs: Vec<String> = config.get_json_array("a", "strs").unwrap();
+        assert_eq!(strs, ["x", "y, z", ""]);
+        assert_eq!(config.get_json_array::<u8>("a", "bad"), None);
+        assert_eq!(config.get_json_array::<u8>("a", "plain"), None);
+        assert_eq!(config.get_json_array::<u8>("a", "missing"), None);
+    }
+
+    #[test]
+    fn line_ending() {
+        let config =
+            Ini::from_string("[a]\n; note\nx = 1\n[b]\ny = 2").line_ending(LineEnding::Crlf);
+        let buffer = config.to_buffer();
+        assert_eq!(buffer, "[a]\r\n; note\r\nx = 1\r\n\r\n[b]\r\ny = 2");
+        assert_eq!(config.serialized_len(), buffer.len());
+        assert_eq!(config.to_string(), buffer);
+
+        let parsed = Ini::from_string(&buffer);
+        assert_eq!(parsed.to_buffer(), "[a]\n; note\nx = 1\n\n[b]\ny = 2");
+        assert_eq!(Ini::new().line_ending(LineEnding::Crlf).to_buffer(), "");
+    }
+
+    #[test]
+    fn get_bytes_size() {
+        let config = Ini::from_string(
+            "[a]\ncache = 256M\nheap = 4GiB\nbare = 512\nspaced = 2 KB\nbad = 1.5G\nunit = 3k\nhuge = 20000000TiB",
+        );
+        assert_eq!(config.get_bytes_size("a", "cache"), Some(256_000_000));
+        assert_eq!(config.get_bytes_size("a", "heap"), Some(4_294_967_296));
+        assert_eq!(config.get_bytes_size("a", "bare"), Some(512));
+        assert_eq!(config.get_bytes_size("a", "spaced"), Some(2000));
+        assert_eq!(config.get_bytes_size("a", "bad"), None);
+        assert_eq!(config.get_bytes_size("a", "unit"), None);
+        assert_eq!(config.get_bytes_size("a", "huge"), None);
+        assert_eq!(config.get_bytes_size("a", "missing"), None);
+    }
+
+    #[test]
+    fn had_parse_errors() {
+        let (config, _) = Ini::from_buffer_counted("[a]\nx = 1\n[b");
+        assert!(config.had_parse_errors());
+        let (config, _) = Ini::from_buffer_counted("[a]\nx = 1\n; [b");
+        assert!(!config.had_parse_errors());
+        let options = ParseOptions::new().max_sections(1);
+        assert!(!Ini::from_string_with_options("[a]\nx = 1", &options)
+            .unwrap()
+            .had_parse_errors());
+        assert!(!Ini::new().section("a").item("x", "1").had_parse_errors());
+    }
+
+    #[test]
+    fn iter_set() {
+        let config = Ini::from_string("[a]\nx = 1\ny =\n[b]\nz =\n[c]\nw = \"\"\nv = 2");
+        let set: Vec<(&str, &str, &str)> = config
+            .iter_set()
+            .map(|(s, k, v)| (s.as_str(), k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(set, [("a", "x", "1"), ("c", "w", "\"\""), ("c", "v", "2")]);
+        assert_eq!(Ini::new().iter_set().count(), 0);
+    }
+
+    #[test]
+    fn get_vec_at() {
+        let config = Ini::from_string("[a]\nlist = 10, 20, x\nescaped = a\\,b, c");
+        assert_eq!(config.get_vec_at::<u8>("a", "list", 0, ","), Some(10));
+        assert_eq!(config.get_vec_at::<u8>("a", "list", 1, ","), Some(20));
+        assert_eq!(config.get_vec_at::<u8>("a", "list", 2, ","), None);
+        assert_eq!(config.get_vec_at::<u8>("a", "list", 3, ","), None);
+        assert_eq!(
+            config.get_vec_at::<String>("a", "escaped", 0, ","),
+            Some("a,b".to_owned())
+        );
+        assert_eq!(config.get_vec_at::<u8>("a", "missing", 0, ","), None);
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let config = Ini::from_string("[a]\na\\= = 1\nb = x=y");
+        let a: Option<u8> = config.get("a", "a=");
+        assert_eq!(a, Some(1));
+        assert_eq!(config.to_buffer(), "[a]\na\\= = 1\nb = x=y");
+
+        let config = Ini::new()
+            .section("a")
+            .item("a\\=", "1")
+            .item("b\\", "2")
+            .item("c\\d", "3");
+        let buffer = config.to_buffer();
+        assert_eq!(buffer, "[a]\na\\\\\\= = 1\nb\\ = 2\nc\\d = 3");
+        assert_eq!(config.serialized_len(), buffer.len());
+        let config = Ini::from_string(&buffer);
+        assert_eq!(config.get::<u8>("a", "a\\="), Some(1));
+        assert_eq!(config.get::<u8>("a", "b\\"), Some(2));
+        assert_eq!(config.get::<u8>("a", "c\\d"), Some(3));
+
+        // backslashes in keys without `\=` are kept
+        let config = Ini::from_string("[a]\nc\\d = 3\na\\\\b = 4");
+        assert_eq!(config.get::<u8>("a", "c\\d"), Some(3));
+        assert_eq!(config.get::<u8>("a", "a\\\\b"), Some(4));
+        assert_eq!(config.to_buffer(), "[a]\nc\\d = 3\na\\\\b = 4");
+    }
+
+    #[test]
+    fn normalize() {
         let mut config = Ini::new()
-            .section("items")
-            .item("a", "1")
-            .item("b", "2")
-            .item("c", "3");
+            .section("  b ")
+            .item(" z ", "  1")
+            .item("y", "two words   ")
+            .section("a")
+            .item("x", " 3 ")
+            .section("b")
+            .item("w", "4")
+            .section(" b")
+            .item("z", "5");
+        config.normalize();
+        assert_eq!(
+            config.to_buffer(),
+            "[b]\nz = 5\ny = two words\nw = 4\n\n[a]\nx = 3"
+        );
 
-        // mutate items
-        for (_, item) in config.iter_mut() {
-            for (_, value) in item {
-                let v: i32 = value.parse().unwrap();
-                *value = format!("{}", v + 1);
+        let mut config = Ini::from_string("; stale\n[empty]\n[a]\nx = 1");
+        config.normalize();
+        config.insert("empty", "y", "2");
+        assert_eq!(config.section_comment("empty"), None);
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\n\n[empty]\ny = 2");
+    }
+
+    #[test]
+    fn is_modified() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\ny = 2");
+        assert!(!config.is_modified());
+        assert!(!config.move_section_before("a", "missing"));
+        assert_eq!(config.trim_empty_sections(), 0);
+        assert!(!config.is_modified());
+
+        config.insert("a", "z", "3");
+        assert!(config.is_modified());
+        config.mark_clean();
+        assert!(!config.is_modified());
+
+        for (_, iter) in config.iter_mut() {
+            for (_, value) in iter {
+                value.push('0');
             }
         }
+        assert!(config.is_modified());
+        config.mark_clean();
+        config.replace_section("b", &[]);
+        assert!(config.is_modified());
+        config.mark_clean();
+        config.set_comment("a", "x", "text");
+        assert!(config.is_modified());
 
-        let a_val: Option<u8> = config.get("items", "a");
-        let b_val: Option<u8> = config.get("items", "b");
-        let c_val: Option<u8> = config.get("items", "c");
+        assert!(Ini::new().section("a").item("x", "1").is_modified());
+        let concat = Ini::concat(&[&config]);
+        assert!(!concat.is_modified());
+    }
 
-        assert_eq!(a_val, Some(2));
-        assert_eq!(b_val, Some(3));
-        assert_eq!(c_val, Some(4));
+    #[test]
+    fn replace_section() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\n; about y\ny = 2\n[c]\nz = 3");
+        config.replace_section("b", &[("w", "4"), ("y", "5")]);
+        config.replace_section("d", &[("v", "6")]);
+        assert_eq!(config.comment_for("b", "y"), None);
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 1\n\n[b]\nw = 4\ny = 5\n\n[c]\nz = 3\n\n[d]\nv = 6"
+        );
     }
 
     #[test]
-    fn redefine_item() {
-        let config = Ini::new()
-            .section("items")
-            .item("one", "3")
-            .item("two", "2")
-            .item("one", "1");
+    fn extract_section() {
+        let config = Ini::from_string("[a]\nx = 1\n[b]\n; about z\nz = 3\ny = 2\n[c]\nw = 4");
+        let b = config.extract_section("b").unwrap();
+        assert_eq!(b.to_buffer(), "[b]\n; about z\nz = 3\ny = 2");
+        assert_eq!(b.total_keys(), 2);
+        assert!(config.extract_section("d").is_none());
+    }
 
-        let one: Option<i32> = config.get("items", "one");
-        assert_eq!(one, Some(1));
+    #[test]
+    fn trim_empty_sections() {
+        let mut config =
+            Ini::from_string("; first\n[a]\n; note\nx = 1 ; inline\n[b]\ny = 2\n[c]\nz = 3");
+        for (name, section) in config.sections_mut() {
+            if name != "b" {
+                section.remove("x");
+                section.remove("z");
+            }
+        }
+        assert_eq!(config.total_keys(), 1);
+        assert_eq!(config.trim_empty_sections(), 2);
+        assert_eq!(config.trim_empty_sections(), 0);
+        assert_eq!(config.to_buffer(), "[b]\ny = 2");
+        assert_eq!(config.section_comment("a"), None);
+
+        config.insert("a", "x", "1");
+        assert_eq!(config.to_buffer(), "[b]\ny = 2\n\n[a]\nx = 1");
     }
 
     #[test]
-    fn redefine_section() {
-        let config = Ini::new()
-            .section("one")
-            .item("a", "1")
-            .section("two")
-            .item("b", "2")
-            .section("one")
-            .item("c", "3");
+    fn sections_mut() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\ny = 2\n[c]\nz = 3");
+        let mut names = Vec::new();
+        for (name, section) in config.sections_mut() {
+            names.push(name.clone());
+            section.insert("added".to_owned(), name.to_uppercase());
+        }
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 1\nadded = A\n\n[b]\ny = 2\nadded = B\n\n[c]\nz = 3\nadded = C"
+        );
+    }
 
-        let a_val: Option<i32> = config.get("one", "a");
-        let c_val: Option<i32> = config.get("one", "c");
+    #[test]
+    fn shrink_to_fit() {
+        let mut config = Ini::new();
+        for i in 0..100 {
+            config.insert("a", format!("k{}", i), i.to_string());
+        }
+        for (_, section) in config.sections_mut() {
+            for i in 1..100 {
+                section.remove(&format!("k{}", i));
+            }
+        }
+        config.shrink_to_fit();
+        assert_eq!(config.to_buffer(), "[a]\nk0 = 0");
+    }
 
-        assert_eq!(a_val, Some(1));
-        assert_eq!(c_val, Some(3));
+    #[test]
+    fn section_header() {
+        let config = Ini::from_string("[ spaced ]\nx = 1\n[b]\ny = 2");
+        let x: Option<u8> = config.get("spaced", "x");
+        assert_eq!(x, Some(1));
+
+        let config = config.section_header(|name| format!("[ {} ]", name.to_uppercase()));
+        assert_eq!(config.to_buffer(), "[ SPACED ]\nx = 1\n\n[ B ]\ny = 2");
     }
 
     #[test]
-    fn with_escaped_items() {
+    fn escaped_values() {
         let config = Ini::new()
-            .section("default")
-            .item("vector", r"1, 2, 3, 4, 5, 6, 7");
+            .section("a")
+            .item("x", "tab\there\0null\\")
+            .escape_values(true);
+        let buffer = config.to_buffer();
+        assert_eq!(
+            buffer,
+            r"[a]
+x = tab\x09here\x00null\\"
+        );
 
-        let vector: Vec<String> = config.get_vec("default", "vector").unwrap();
-        assert_eq!(vector, ["1", "2", "3", "4", "5", "6", "7"]);
+        let options = ParseOptions::new().unescape_values(true);
+        let config = Ini::from_string_with_options(&buffer, &options).unwrap();
+        let x: String = config.get("a", "x").unwrap();
+        assert_eq!(x, "tab\there\0null\\");
     }
 
     #[test]
-    fn use_item_vec() {
+    fn prefix_sections() {
+        let mut config = Ini::from_string("[server]\nport = 80\n[db]\nname = main")
+            .item_vec_with_sep("hosts", &["a", "b"], "|");
+        config.prefix_sections("app.");
+
+        let port: Option<u16> = config.get("app.server", "port");
+        let hosts: Option<Vec<String>> = config.get_vec("app.db", "hosts");
+        assert_eq!(port, Some(80));
+        assert_eq!(hosts, Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(config.get::<u16>("server", "port"), None);
+        assert_eq!(
+            config.to_buffer(),
+            "[app.server]\nport = 80\n\n[app.db]\nname = main\nhosts = a|b"
+        );
+
+        config.strip_prefix("app.");
+        let port: Option<u16> = config.get("server", "port");
+        assert_eq!(port, Some(80));
+    }
+
+    #[test]
+    fn get_set() {
+        let config = Ini::from_string("[a]\nlist = 1, 2, 2, 3\nbad = 1, x");
+        let list: HashSet<u32> = config.get_set("a", "list").unwrap();
+        assert_eq!(list.len(), 3);
+        assert!(list.contains(&1) && list.contains(&2) && list.contains(&3));
+        assert_eq!(config.get_set::<u32>("a", "bad"), None);
+    }
+
+    #[test]
+    fn set_key_order() {
+        let mut config = Ini::from_string("[a]\nw = 1\nx = 2\ny = 3\nz = 4");
+        config.set_key_order("missing", &["x"]);
+        config.set_key_order("a", &["w", "x"]);
+        assert!(!config.is_modified());
+        config.set_key_order("a", &["z", "missing", "x", "w"]);
+        assert!(config.is_modified());
+        assert_eq!(config.to_buffer(), "[a]\nz = 4\nx = 2\nw = 1\ny = 3");
+    }
+
+    #[test]
+    fn move_section() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\ny = 2\n[c]\nz = 3");
+        assert!(config.move_section_before("c", "a"));
+        assert_eq!(config.to_buffer(), "[c]\nz = 3\n\n[a]\nx = 1\n\n[b]\ny = 2");
+        assert!(config.move_section_after("c", "b"));
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\n\n[b]\ny = 2\n\n[c]\nz = 3");
+        assert!(config.move_section_after("a", "a"));
+        assert!(!config.move_section_before("a", "missing"));
+        assert!(!config.move_section_after("missing", "a"));
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\n\n[b]\ny = 2\n\n[c]\nz = 3");
+    }
+
+    #[test]
+    fn set_section_order() {
+        let mut config = Ini::from_string("[a]\nx = 1\n[b]\ny = 2\n[c]\nz = 3\n[d]\nw = 4");
+        config.set_section_order(&["a", "missing", "b"]);
+        assert!(!config.is_modified());
+        config.set_section_order(&["c", "missing", "a", "b"]);
+        assert!(config.is_modified());
+        assert_eq!(
+            config.to_buffer(),
+            "[c]\nz = 3\n\n[a]\nx = 1\n\n[b]\ny = 2\n\n[d]\nw = 4"
+        );
+    }
+
+    #[test]
+    fn find_by_value() {
+        let config = Ini::from_string("[a]\nx = 1\ny = 2\nz = 2");
+        assert_eq!(config.find_key_by_value("a", "2"), Some(&"y".to_owned()));
+        assert_eq!(config.find_key_by_value("a", "3"), None);
+        assert_eq!(config.find_key_by_value("b", "1"), None);
+        assert!(config.section_contains_value("a", "1"));
+        assert!(!config.section_contains_value("a", "3"));
+        assert!(!config.section_contains_value("b", "1"));
+    }
+
+    #[test]
+    fn include() {
+        let dir = temp_path("include");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(
+            dir.join("main.ini"),
+            "[a]\nx = 1\ninclude = sub/db.ini\ny = 3",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sub/db.ini"),
+            "y = 2\nz = 4\n[db]\ninclude = ../common.ini",
+        )
+        .unwrap();
+        fs::write(dir.join("common.ini"), "[db]\nname = main").unwrap();
+        fs::write(dir.join("cycle.ini"), "[a]\ninclude = sub/cycle.ini").unwrap();
+        fs::write(dir.join("sub/cycle.ini"), "[b]\ninclude = ../cycle.ini").unwrap();
+
+        let options = ParseOptions::new().include_key("include");
+        let config = Ini::from_file_with_options(&dir.join("main.ini"), &options);
+        let cycle = Ini::from_file_with_options(&dir.join("cycle.ini"), &options);
+        let plain = Ini::from_file_with_options(&dir.join("main.ini"), &ParseOptions::new());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            config.unwrap().to_buffer(),
+            "[a]\nx = 1\ny = 3\nz = 4\n\n[db]\nname = main"
+        );
+        assert!(matches!(cycle, Err(IniError::IncludeCycle(_))));
+        let include: Option<String> = plain.unwrap().get("a", "include");
+        assert_eq!(include, Some("sub/db.ini".to_owned()));
+    }
+
+    #[test]
+    fn get_duration() {
+        let config = Ini::from_string(
+            "[a]\nms = 1500ms\ns = 30s\nm = 2 m\nh = 1.5h\nd = 1d\nbare = 10\nbad = 5w\nneg = -1s\nnan = NaN",
+        );
+        assert_eq!(
+            config.get_duration("a", "ms"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(config.get_duration("a", "s"), Some(Duration::from_secs(30)));
+        assert_eq!(
+            config.get_duration("a", "m"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            config.get_duration("a", "h"),
+            Some(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            config.get_duration("a", "d"),
+            Some(Duration::from_secs(86400))
+        );
+        assert_eq!(
+            config.get_duration("a", "bare"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(config.get_duration("a", "bad"), None);
+        assert_eq!(config.get_duration("a", "neg"), None);
+        assert_eq!(config.get_duration("a", "nan"), None);
+        assert_eq!(config.get_duration("a", "missing"), None);
+
         let config =
+            Ini::from_string("[a]\nd = 1000000000000000000000000d\ns = 18446744073709551615");
+        assert_eq!(config.get_duration("a", "d"), None);
+        assert_eq!(config.get_duration("a", "s"), None);
+    }
+
+    #[test]
+    fn total_keys() {
+        let config = Ini::from_string("[a]\nx = 1\ny = 2\nz = 3\n[b]\nw = 4\n[a]\nx = 5");
+        assert_eq!(config.total_keys(), 4);
+        assert_eq!(config.total_keys_in("a"), 3);
+        assert_eq!(config.total_keys_in("b"), 1);
+        assert_eq!(config.total_keys_in("c"), 0);
+        assert_eq!(Ini::new().total_keys(), 0);
+    }
+
+    #[test]
+    fn serialized_len() {
+        let mut commented =
+            Ini::from_string("[a]\n; one\n; two\nx = 1 ; not kept\n[b]\nk\\=ey = é");
+        commented.set_inline_comment("b", "k=ey", "inline");
+        let configs = [
+            Ini::new(),
+            Ini::from_string("[a]\nx = 1\n[b]\ny = hello, world"),
+            commented,
             Ini::new()
-                .section("default")
-                .item_vec_with_sep("a", &["a,b", "c,d", "e"], "|");
+                .section("a")
+                .item("tab", "a\tb\\")
+                .escape_values(true)
+                .section_header(|name| format!("[ {} ]", name)),
+        ];
+        for config in &configs {
+            assert_eq!(config.serialized_len(), config.to_buffer().len());
+        }
+    }
 
-        let v: Vec<String> = config.get_vec_with_sep("default", "a", "|").unwrap();
-        assert_eq!(v, [r"a,b", "c,d", "e"]);
+    #[test]
+    fn to_buffer_filtered() {
+        let config = Ini::from_string(
+            "[server.a]\nport = 80\n[db]\nport = 5432\n[server.b]\nport = 81\n[server]\nx = 1",
+        );
+        assert_eq!(
+            config.to_buffer_filtered(|name| name.starts_with("server.")),
+            "[server.a]\nport = 80\n\n[server.b]\nport = 81"
+        );
+        assert_eq!(config.to_buffer_filtered(|_| false), "");
+    }
+
+    #[test]
+    fn get_map() {
+        let config = Ini::new()
+            .section("a")
+            .item("opts", "a=1;b=2")
+            .item("messy", " a = 1 ;; b ; c=x=y;");
+        let opts = config.get_map("a", "opts", ";", "=").unwrap();
+        assert_eq!(opts.len(), 2);
+        assert_eq!(opts["a"], "1");
+        assert_eq!(opts["b"], "2");
+
+        let messy = config.get_map("a", "messy", ";", "=").unwrap();
+        assert_eq!(messy.len(), 3);
+        assert_eq!(messy["a"], "1");
+        assert_eq!(messy["b"], "");
+        assert_eq!(messy["c"], "x=y");
+        assert_eq!(config.get_map("a", "missing", ";", "="), None);
+    }
+
+    #[test]
+    fn get_cow() {
+        let config = Ini::from_string("[a]\nx = value");
+        let stored = config.get_raw("a", "x").unwrap();
+        match config.get_cow("a", "x") {
+            Some(Cow::Borrowed(value)) => assert_eq!(value.as_ptr(), stored.as_ptr()),
+            _ => unreachable!(),
+        }
+        assert_eq!(config.get_cow("a", "y"), None);
+    }
+
+    #[test]
+    fn from_buffer_with_handler() {
+        let mut errors = Vec::new();
+        let config =
+            Ini::from_buffer_with_handler("[a]\nx = 1\n[b\ny = 2\njunk", |line, text, msg| {
+                errors.push((line, text.to_owned(), msg.to_owned()));
+            });
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            (3, "[b".to_owned(), "incorrect section syntax".to_owned())
+        );
+        assert_eq!(errors[1].0, 5);
+        assert_eq!(errors[1].1, "junk");
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\ny = 2");
+    }
+
+    #[test]
+    fn from_buffer_counted() {
+        let (config, errors) = Ini::from_buffer_counted("[a]\nx = 1\n[b\ny = 2\n= 3\n; ok");
+        assert_eq!(errors, 2);
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\ny = 2");
+        let (_, errors) = Ini::from_buffer_counted("[a]\nx = 1");
+        assert_eq!(errors, 0);
+    }
+
+    #[test]
+    fn get_ref() {
+        let config = Ini::from_string("[a]\nx = value");
+        let stored = config.get_raw("a", "x").unwrap();
+        let value = config.get_ref("a", "x").unwrap();
+        assert_eq!(value, "value");
+        assert_eq!(value.as_ptr(), stored.as_ptr());
+        assert_eq!(config.get_ref("a", "y"), None);
+    }
+
+    #[test]
+    fn from_lines() {
+        let lines = vec!["[a]", "; comment", "x = 1", "", "[b]", "y = 2"];
+        let config = Ini::from_lines(lines);
+        assert_eq!(config.to_buffer(), "[a]\n; comment\nx = 1\n\n[b]\ny = 2");
+
+        let owned: Vec<String> = vec!["[a]".to_owned(), "x = 1".to_owned()];
+        let config = Ini::from_lines(owned);
+        let x: Option<u8> = config.get("a", "x");
+        assert_eq!(x, Some(1));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn apply_env_overrides() {
+        std::env::set_var("TINI_TEST_DB_NAME", "override");
+        std::env::set_var("TINI_TEST_DB_MISSING", "ignored");
+        let mut config = Ini::from_string("[db]\nname = main\nhost = localhost");
+        config.apply_env_overrides("tini.test");
+        std::env::remove_var("TINI_TEST_DB_NAME");
+        std::env::remove_var("TINI_TEST_DB_MISSING");
+
+        assert_eq!(
+            config.to_buffer(),
+            "[db]\nname = override\nhost = localhost"
+        );
+    }
+
+    #[test]
+    fn to_json_string() {
+        let config = Ini::new()
+            .section("b")
+            .item("quote", r#"say "hi""#)
+            .item("path", r"C:\dir")
+            .section("a")
+            .item("ctrl", "line\nnext\u{1}");
+        assert_eq!(
+            config.to_json_string(),
+            r#"{"b": {"quote": "say \"hi\"", "path": "C:\\dir"}, "a": {"ctrl": "line\nnext\u0001"}}"#
+        );
+        assert_eq!(Ini::new().to_json_string(), "{}");
+    }
+
+    #[test]
+    fn multi_char_separator() {
+        let config = Ini::new()
+            .section("a")
+            .item_vec_with_sep("list", &["x|y", "z", "w |"], " | ")
+            .item_vec_with_sep("whole", &["x,y"], "");
+        let list: Vec<String> = config.get_vec("a", "list").unwrap();
+        assert_eq!(list, ["x|y", "z", "w |"]);
+        let whole: Vec<String> = config.get_vec("a", "whole").unwrap();
+        assert_eq!(whole, ["x,y"]);
+
+        let config = Ini::from_string(&config.to_buffer());
+        let list: Vec<String> = config.get_vec_with_sep("a", "list", " | ").unwrap();
+        assert_eq!(list, ["x|y", "z", "w |"]);
+    }
+
+    #[test]
+    fn escaped_separators() {
+        let config = Ini::new()
+            .section("a")
+            .item_vec("list", &["x,y", "z", ",", "w, v"])
+            .item_vec_with_sep("pipes", &["a|b", "c"], " | ");
+        assert_eq!(
+            config.to_buffer(),
+            r"[a]
+list = x\,y, z, \,, w\, v
+pipes = a\|b | c"
+        );
+        let list: Vec<String> = config.get_vec("a", "list").unwrap();
+        assert_eq!(list, ["x,y", "z", ",", "w, v"]);
+
+        let config = Ini::from_string(&config.to_buffer());
+        let list: Vec<String> = config.get_vec("a", "list").unwrap();
+        assert_eq!(list, ["x,y", "z", ",", "w, v"]);
+        let pipes: Vec<String> = config.get_vec_with_sep("a", "pipes", "|").unwrap();
+        assert_eq!(pipes, ["a|b", "c"]);
+
+        let config = Ini::new()
+            .section("a")
+            .item_vec("paths", &["C:\\dir\\", "D:", "\\\\server\\share"]);
+        assert_eq!(
+            config.to_buffer(),
+            r"[a]
+paths = C:\\dir\\, D:, \\\\server\\share"
+        );
+        let config = Ini::from_string(&config.to_buffer());
+        let paths: Vec<String> = config.get_vec("a", "paths").unwrap();
+        assert_eq!(paths, ["C:\\dir\\", "D:", "\\\\server\\share"]);
+
+        // hand-written Windows paths: `\\` and `\,` are escapes, other backslashes are kept
+        let config = Ini::from_string(
+            r"[a]
+paths = C:\dir\\,D:\x
+joined = C:\dir\,D:\x",
+        );
+        let paths: Vec<String> = config.get_vec("a", "paths").unwrap();
+        assert_eq!(paths, [r"C:\dir\", r"D:\x"]);
+        let joined: Vec<String> = config.get_vec("a", "joined").unwrap();
+        assert_eq!(joined, [r"C:\dir,D:\x"]);
+
+        // single backslashes in existing files are kept
+        let config = Ini::from_string(
+            r"[a]
+paths = C:\a,b",
+        );
+        let paths: Vec<String> = config.get_vec("a", "paths").unwrap();
+        assert_eq!(paths, ["C:\\a", "b"]);
+    }
+
+    #[test]
+    fn dedup_sections() {
+        let buffer = "[a]\nx = 1\n[b]\ny = 2\n[a]\nz = 3\n[a]\nx = 4\n[b]";
+        let options = ParseOptions::new().dedup_sections(true);
+        let config = Ini::from_string_with_options(buffer, &options).unwrap();
+        assert_eq!(config.merged_sections(), ["a", "b"]);
+        assert_eq!(config.to_buffer(), "[a]\nx = 4\nz = 3\n\n[b]\ny = 2");
+
+        let config = Ini::from_string_with_options(buffer, &ParseOptions::new()).unwrap();
+        assert!(config.merged_sections().is_empty());
+    }
+
+    #[test]
+    fn concat() {
+        let first = Ini::from_string("[a]\nx = 1\ny = 2");
+        let second = Ini::from_string("[b]\nz = 3");
+        let third = Ini::from_string("[c]\nw = 4\n[a]\ny = 5\nv = 6");
+        let config = Ini::concat(&[&first, &second, &third]);
+        assert_eq!(
+            config.to_buffer(),
+            "[a]\nx = 1\ny = 5\nv = 6\n\n[b]\nz = 3\n\n[c]\nw = 4"
+        );
+        assert_eq!(Ini::concat(&[]).to_buffer(), "");
+    }
+
+    #[test]
+    fn iter_vec() {
+        let config = Ini::from_string("[a]\nlist = 10, 20 , 30\nwhole = a,b");
+        let mut sum = 0;
+        for element in config.iter_vec("a", "list", ",").unwrap() {
+            sum += element.parse::<i32>().unwrap();
+        }
+        assert_eq!(sum, 60);
+        let whole: Vec<&str> = config.iter_vec("a", "whole", "").unwrap().collect();
+        assert_eq!(whole, ["a,b"]);
+        assert!(config.iter_vec("a", "missing", ",").is_none());
+    }
+
+    #[test]
+    fn parse_limits() {
+        let options = ParseOptions::new().max_sections(2);
+        let config = Ini::from_string_with_options("[a]\nx = 1\n[b]\n[a]\ny = 2", &options);
+        assert_eq!(config.unwrap().to_buffer(), "[a]\nx = 1\ny = 2");
+        let err = Ini::from_string_with_options("[a]\n[b]\n[a]\n[c]", &options).unwrap_err();
+        assert_eq!(err.line(), 4);
+        assert_eq!(err.message(), "too many sections");
+
+        let options = ParseOptions::new().max_keys_per_section(2);
+        let buffer = "[a]\nx = 1\ny = 2\nx = 3\n[b]\nz = 4";
+        let config = Ini::from_string_with_options(buffer, &options).unwrap();
+        assert_eq!(config.to_buffer(), "[a]\nx = 3\ny = 2\n\n[b]\nz = 4");
+        let err = Ini::from_string_with_options("[a]\nx = 1\ny = 2\nz = 3", &options).unwrap_err();
+        assert_eq!(err.line(), 4);
+        assert_eq!(err.message(), "too many keys in section");
+
+        let options = ParseOptions::new().max_line_length(9);
+        let config = Ini::from_string_with_options("[a]\nx = 12345", &options).unwrap();
+        assert_eq!(config.get::<u32>("a", "x"), Some(12345));
+        let err = Ini::from_string_with_options("[a]\nx = 123456", &options).unwrap_err();
+        assert_eq!(err.line(), 2);
+        assert_eq!(err.message(), "line too long");
+    }
+
+    #[test]
+    fn from_hash_map() {
+        let mut a = HashMap::new();
+        a.insert("y".to_owned(), "2".to_owned());
+        a.insert("x".to_owned(), "1".to_owned());
+        let mut map = HashMap::new();
+        map.insert("b".to_owned(), HashMap::new());
+        map.insert("a".to_owned(), a);
+        let config = Ini::from(map);
+        let x: Option<u8> = config.get("a", "x");
+        assert_eq!(x, Some(1));
+        assert_eq!(config.total_keys_in("b"), 0);
+        assert_eq!(config.to_buffer(), "[a]\nx = 1\ny = 2\n\n[b]");
+    }
+
+    #[test]
+    fn view() {
+        let config = Ini::from_string("[b]\nx = 1\nlist = 1, 2\n[a]\ny = 2");
+        let view = config.view();
+        assert_eq!(view.get::<u8>("b", "x"), config.get::<u8>("b", "x"));
+        assert_eq!(view.get::<u8>("b", "y"), None);
+        assert_eq!(
+            view.get_vec::<u8>("b", "list"),
+            config.get_vec::<u8>("b", "list")
+        );
+        let sections: Vec<&str> = view.sections().collect();
+        assert_eq!(sections, ["b", "a"]);
+        let view_keys: Vec<&String> = view
+            .iter()
+            .flat_map(|(_, iter)| iter.map(|(k, _)| k))
+            .collect();
+        let keys: Vec<&String> = config
+            .iter()
+            .flat_map(|(_, iter)| iter.map(|(k, _)| k))
+            .collect();
+        assert_eq!(view_keys, keys);
+    }
+
+    #[test]
+    fn index() {
+        let config = Ini::from_string("[a]\nx = 1\n[b]\ny = hello\nref = ${a:x}");
+        assert_eq!(config["a"]["x"], "1");
+        assert_eq!(&config["b"]["y"], "hello");
+        // stored text, references are not resolved
+        assert_eq!(config["b"]["ref"], "${a:x}");
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found: \"y\"")]
+    fn index_missing_key() {
+        let config = Ini::from_string("[a]\nx = 1");
+        let _ = &config["a"]["y"];
+    }
+
+    #[test]
+    #[should_panic(expected = "section not found: \"b\"")]
+    fn index_missing_section() {
+        let config = Ini::from_string("[a]\nx = 1");
+        let _ = &config["b"];
     }
 }
@@ -1,20 +1,51 @@
+//! Insertion-ordered hash map
+//!
+//! [`OrderedHashMap`](struct.OrderedHashMap.html) is the map tini uses to store sections and
+//! their keys. It is a `HashMap` which additionally remembers the order in which keys were first
+//! inserted:
+//!
+//! * [`iter()`](struct.OrderedHashMap.html#method.iter) and
+//!   [`keys()`](struct.OrderedHashMap.html#method.keys) yield entries in insertion order;
+//! * inserting an existing key again (with [`insert()`](struct.OrderedHashMap.html#method.insert)
+//!   or [`entry()`](struct.OrderedHashMap.html#method.entry)) replaces the value but keeps the
+//!   key at its original position;
+//! * [`remove()`](struct.OrderedHashMap.html#method.remove) drops the key from the order, so
+//!   inserting it again appends it to the end.
 use std::borrow::Borrow;
-use std::collections::hash_map::{self, Entry};
+use std::collections::hash_map;
 use std::collections::HashMap;
+use std::fmt::Debug;
 use std::hash::Hash;
 use std::iter::IntoIterator;
+use std::ops::Index;
 
+/// Hash map preserving insertion order of keys
+///
+/// # Example
+/// ```
+/// # use tini::OrderedHashMap;
+/// let mut map = OrderedHashMap::new();
+/// map.insert("c", 1);
+/// map.insert("a", 2);
+/// map.insert("b", 3);
+/// map.insert("c", 4);
+/// let pairs: Vec<_> = map.iter().collect();
+/// assert_eq!(pairs, [(&"c", &4), (&"a", &2), (&"b", &3)]);
+/// ```
 #[derive(Debug)]
 pub struct OrderedHashMap<K, V> {
     base: HashMap<K, V>,
     order: Vec<K>,
 }
 
+/// Iterator over entries of [`OrderedHashMap`](struct.OrderedHashMap.html) in insertion order
 pub struct Iter<'a, K, V> {
     base: &'a HashMap<K, V>,
     order_iterator: std::slice::Iter<'a, K>,
 }
 
+/// Mutable iterator over entries of [`OrderedHashMap`](struct.OrderedHashMap.html) in arbitrary
+/// order
 pub type IterMut<'a, K, V> = hash_map::IterMut<'a, K, V>;
 
 impl<'a, K, V> IntoIterator for &'a OrderedHashMap<K, V>
@@ -39,12 +70,31 @@ where
     type Item = (&'a K, &'a V);
     fn next(&mut self) -> Option<Self::Item> {
         match self.order_iterator.next() {
-            Some(k) => self.base.get_key_value(&k),
+            Some(k) => self.base.get_key_value(k),
             None => None,
         }
     }
 }
 
+impl<K, Q, V> Index<&Q> for OrderedHashMap<K, V>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Eq + Hash + Debug + ?Sized,
+{
+    type Output = V;
+
+    /// Get value of `key`
+    ///
+    /// # Panics
+    /// Panics if `key` is not present in the map.
+    fn index(&self, key: &Q) -> &V {
+        match self.base.get(key) {
+            Some(value) => value,
+            None => panic!("key not found: {:?}", key),
+        }
+    }
+}
+
 impl<K, V> Default for OrderedHashMap<K, V>
 where
     K: Eq + Hash + Clone,
@@ -58,41 +108,385 @@ impl<K, V> OrderedHashMap<K, V>
 where
     K: Eq + Hash + Clone,
 {
+    /// Create an empty map
     pub fn new() -> OrderedHashMap<K, V> {
         OrderedHashMap {
             base: HashMap::<K, V>::new(),
             order: Vec::<K>::new(),
         }
     }
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+
+    /// Create an empty map with space for at least `capacity` keys
+    pub fn with_capacity(capacity: usize) -> OrderedHashMap<K, V> {
+        OrderedHashMap {
+            base: HashMap::<K, V>::with_capacity(capacity),
+            order: Vec::<K>::with_capacity(capacity),
+        }
+    }
+
+    /// Number of keys in the map
+    pub fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    /// Check if the map contains no keys
+    pub fn is_empty(&self) -> bool {
+        self.base.is_empty()
+    }
+
+    /// Shrink capacity of the map as much as possible
+    pub fn shrink_to_fit(&mut self) {
+        self.base.shrink_to_fit();
+        self.order.shrink_to_fit();
+    }
+
+    /// Check if the map contains a key
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.base.contains_key(k)
+    }
+
+    /// Get value of a key
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         self.base.get(k)
     }
+
+    /// Get mutable reference to value of a key
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.base.get_mut(k)
+    }
+
+    /// Insert a key-value pair and return the previous value of the key
+    ///
+    /// New keys are appended to the order, existing keys keep their position.
     pub fn insert(&mut self, k: K, v: V) -> Option<V> {
         if !self.base.contains_key(&k) {
             self.order.push(k.clone());
         }
         self.base.insert(k, v)
     }
+
+    /// Remove a key and return its value
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::OrderedHashMap;
+    /// let mut map = OrderedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// assert_eq!(map.remove("a"), Some(1));
+    /// map.insert("a", 3);
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&"b", &"a"]);
+    /// ```
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let value = self.base.remove(k)?;
+        self.order.retain(|key| key.borrow() != k);
+        Some(value)
+    }
+
+    /// Move `keys` to the beginning of the order in the given sequence
+    ///
+    /// Keys absent from the map are ignored, keys not listed follow in their current relative
+    /// order.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::OrderedHashMap;
+    /// let mut map = OrderedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.set_order(&["c", "x", "a"]);
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&"c", &"a", &"b"]);
+    /// ```
+    pub fn set_order<Q>(&mut self, keys: &[&Q])
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut rest = std::mem::take(&mut self.order);
+        for key in keys {
+            if let Some(pos) = rest.iter().position(|k| k.borrow() == *key) {
+                self.order.push(rest.remove(pos));
+            }
+        }
+        self.order.append(&mut rest);
+    }
+
+    /// Move key `k` directly before key `target` in the order
+    ///
+    /// Returns `false` and leaves the order untouched if either key is absent from the map.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::OrderedHashMap;
+    /// let mut map = OrderedHashMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// assert!(map.move_before("c", "a"));
+    /// assert!(!map.move_before("c", "x"));
+    /// let keys: Vec<_> = map.keys().collect();
+    /// assert_eq!(keys, [&"c", &"a", &"b"]);
+    /// ```
+    pub fn move_before<Q>(&mut self, k: &Q, target: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.move_key(k, target, 0)
+    }
+
+    /// Move key `k` directly after key `target` in the order
+    ///
+    /// Returns `false` and leaves the order untouched if either key is absent from the map.
+    pub fn move_after<Q>(&mut self, k: &Q, target: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.move_key(k, target, 1)
+    }
+
+    /// Move key `k` to position of `target` plus `offset`
+    fn move_key<Q>(&mut self, k: &Q, target: &Q, offset: usize) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.base.contains_key(k) || !self.base.contains_key(target) {
+            return false;
+        }
+        if k == target {
+            return true;
+        }
+        let pos = self.order.iter().position(|key| key.borrow() == k);
+        let key = self.order.remove(pos.expect("key in order"));
+        let pos = self.order.iter().position(|key| key.borrow() == target);
+        self.order.insert(pos.expect("key in order") + offset, key);
+        true
+    }
+
+    /// Iterate over key-value pairs in insertion order
     pub fn iter(&self) -> Iter<'_, K, V> {
         Iter {
             base: &self.base,
             order_iterator: self.order.iter(),
         }
     }
+
+    /// Iterate over key-value pairs with mutable values in arbitrary order
     pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
         self.base.iter_mut()
     }
-    pub fn keys(&self) -> std::slice::Iter<K> {
+
+    /// Iterate over key-value pairs with mutable values in insertion order
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::OrderedHashMap;
+    /// let mut map = OrderedHashMap::new();
+    /// map.insert("b", 1);
+    /// map.insert("a", 2);
+    /// let mut keys = Vec::new();
+    /// for (key, value) in map.iter_mut_ordered() {
+    ///     keys.push(*key);
+    ///     *value += 1;
+    /// }
+    /// assert_eq!(keys, ["b", "a"]);
+    /// assert_eq!(map.get("a"), Some(&3));
+    /// ```
+    pub fn iter_mut_ordered(&mut self) -> impl Iterator<Item = (&K, &mut V)> + '_ {
+        let mut values: HashMap<&K, &mut V> = self.base.iter_mut().collect();
+        self.order
+            .iter()
+            .filter_map(move |key| values.remove(key).map(|value| (key, value)))
+    }
+
+    /// Iterate over keys in insertion order
+    pub fn keys(&self) -> std::slice::Iter<'_, K> {
         self.order.iter()
     }
+
+    /// Get entry of a key for in-place manipulation
+    ///
+    /// A new key takes the position after all keys present in the map once a value is inserted
+    /// through the entry.
+    ///
+    /// # Example
+    /// ```
+    /// # use tini::OrderedHashMap;
+    /// let mut map = OrderedHashMap::new();
+    /// map.insert("b", 1);
+    /// *map.entry("a").or_insert(0) += 1;
+    /// *map.entry("b").or_insert(0) += 1;
+    /// let pairs: Vec<_> = map.iter().collect();
+    /// assert_eq!(pairs, [(&"b", &2), (&"a", &1)]);
+    /// ```
     pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
-        if !self.base.contains_key(&key) {
-            self.order.push(key.clone());
+        match self.base.entry(key) {
+            hash_map::Entry::Occupied(inner) => Entry::Occupied(OccupiedEntry { inner }),
+            hash_map::Entry::Vacant(inner) => Entry::Vacant(VacantEntry {
+                inner,
+                order: &mut self.order,
+            }),
         }
-        self.base.entry(key)
+    }
+}
+
+/// Entry of [`OrderedHashMap`](struct.OrderedHashMap.html) returned by
+/// [`entry()`](struct.OrderedHashMap.html#method.entry)
+pub enum Entry<'a, K, V> {
+    /// Entry of a key present in the map
+    Occupied(OccupiedEntry<'a, K, V>),
+    /// Entry of a key absent from the map
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+/// Entry of a key present in the map
+pub struct OccupiedEntry<'a, K, V> {
+    inner: hash_map::OccupiedEntry<'a, K, V>,
+}
+
+/// Entry of a key absent from the map
+///
+/// The key is added to the order only when a value is inserted.
+pub struct VacantEntry<'a, K, V> {
+    inner: hash_map::VacantEntry<'a, K, V>,
+    order: &'a mut Vec<K>,
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Clone,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Insert `default` if the entry is vacant and return mutable reference to the value
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Insert result of `default` if the entry is vacant and return mutable reference to the
+    /// value
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Modify the value if the entry is occupied
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Clone,
+    V: Default,
+{
+    /// Insert default value if the entry is vacant and return mutable reference to the value
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    /// Reference to the value
+    pub fn get(&self) -> &V {
+        self.inner.get()
+    }
+
+    /// Mutable reference to the value
+    pub fn get_mut(&mut self) -> &mut V {
+        self.inner.get_mut()
+    }
+
+    /// Convert the entry into mutable reference to the value
+    pub fn into_mut(self) -> &'a mut V {
+        self.inner.into_mut()
+    }
+
+    /// Replace the value and return the old one. The key keeps its position.
+    pub fn insert(&mut self, value: V) -> V {
+        self.inner.insert(value)
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V>
+where
+    K: Clone,
+{
+    /// Key of the entry
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    /// Insert the value, appending the key to the order
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.order.push(self.inner.key().clone());
+        self.inner.insert(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unused_vacant_entry() {
+        let mut map = OrderedHashMap::new();
+        map.insert("a", 1);
+        match map.entry("b") {
+            Entry::Vacant(_) => (),
+            Entry::Occupied(_) => unreachable!(),
+        }
+        let _ = map.entry("c").and_modify(|v| *v += 1);
+
+        assert_eq!(map.order, ["a"]);
+        assert_eq!(map.base.len(), 1);
+
+        map.entry("c").or_insert(3);
+        assert_eq!(map.order, ["a", "c"]);
+        assert_eq!(map.base.len(), 2);
     }
 }
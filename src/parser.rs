@@ -1,62 +1,681 @@
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+/// Options for [`Ini::from_buffer_with_options()`](struct.Ini.html#method.from_buffer_with_options)
+///
+/// # Example
+/// ```
+/// # use tini::ParseOptions;
+/// let options = ParseOptions::new().require_section_header(true);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub(crate) require_section_header: bool,
+    pub(crate) whitespace_separator: bool,
+    pub(crate) separator: Option<String>,
+    pub(crate) unescape_values: bool,
+    pub(crate) include_key: Option<String>,
+    pub(crate) dedup_sections: bool,
+    pub(crate) unquote_keys: bool,
+    pub(crate) max_sections: Option<usize>,
+    pub(crate) max_keys_per_section: Option<usize>,
+    pub(crate) max_line_length: Option<usize>,
+    pub(crate) inline_comments: bool,
+    pub(crate) treat_blank_as_missing: bool,
+    pub(crate) indented_keys: bool,
+}
+
+impl ParseOptions {
+    /// Create default options
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Reject key-value pairs which appear before the first section header
+    pub fn require_section_header(mut self, value: bool) -> Self {
+        self.require_section_header = value;
+        self
+    }
+
+    /// Treat the first run of whitespace as key-value separator in lines without `=`, like in
+    /// Java property files: `key<TAB>value` or `key value with spaces`
+    pub fn whitespace_separator(mut self, value: bool) -> Self {
+        self.whitespace_separator = value;
+        self
+    }
+
+    /// Split key-value pairs at the first occurrence of `value` instead of `=`, e.g. `==` for
+    /// `key == value`; keys can't contain it
+    ///
+    /// [`Ini::to_buffer()`](struct.Ini.html#method.to_buffer) still writes ` = ` between keys
+    /// and values, so such configs don't round-trip.
+    ///
+    /// # Panics
+    /// Panics if `value` is empty.
+    pub fn separator<S: Into<String>>(mut self, value: S) -> Self {
+        let value = value.into();
+        assert!(!value.is_empty(), "separator must not be empty");
+        self.separator = Some(value);
+        self
+    }
+
+    /// Treat keys named `key` as directives to include another file, see
+    /// [`Ini::from_file_with_options()`](struct.Ini.html#method.from_file_with_options)
+    pub fn include_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.include_key = Some(key.into());
+        self
+    }
+
+    /// Strip matching single or double quotes around keys, e.g. `"my key" = value`; quoted keys
+    /// may contain `=`
+    pub fn unquote_keys(mut self, value: bool) -> Self {
+        self.unquote_keys = value;
+        self
+    }
+
+    /// Keep comments following values on the same line, e.g. `port = 8080 ; production`, see
+    /// [`Ini::inline_comment_for()`](struct.Ini.html#method.inline_comment_for)
+    pub fn inline_comments(mut self, value: bool) -> Self {
+        self.inline_comments = value;
+        self
+    }
+
+    /// Skip keys with empty or whitespace-only values, like `key =`, so that
+    /// [`Ini::get::<String>()`](struct.Ini.html#method.get) returns `None` for them instead of
+    /// `Some("")`
+    pub fn treat_blank_as_missing(mut self, value: bool) -> Self {
+        self.treat_blank_as_missing = value;
+        self
+    }
+
+    /// Turn keys indented below an unindented `name:` line into `name.key`, for configs like
+    /// `server:` followed by `  port: 80`; indented lines may use `:` or `=` as separator.
+    /// Only one level of indentation is supported, and keys are written back as `name.key`
+    pub fn indented_keys(mut self, value: bool) -> Self {
+        self.indented_keys = value;
+        self
+    }
+
+    /// Report sections defined more than once; their keys are merged in any case, see
+    /// [`Ini::merged_sections()`](struct.Ini.html#method.merged_sections)
+    pub fn dedup_sections(mut self, value: bool) -> Self {
+        self.dedup_sections = value;
+        self
+    }
+
+    /// Reject input with more than `value` distinct sections, to bound memory use on untrusted
+    /// input
+    pub fn max_sections(mut self, value: usize) -> Self {
+        self.max_sections = Some(value);
+        self
+    }
+
+    /// Reject input with more than `value` distinct keys in one section
+    pub fn max_keys_per_section(mut self, value: usize) -> Self {
+        self.max_keys_per_section = Some(value);
+        self
+    }
+
+    /// Reject lines longer than `value` bytes, not counting the line break
+    pub fn max_line_length(mut self, value: usize) -> Self {
+        self.max_line_length = Some(value);
+        self
+    }
+
+    /// Decode `\\` and `\xNN` escapes in values, as written by Ini with
+    /// [`escape_values`](struct.Ini.html#method.escape_values) enabled
+    pub fn unescape_values(mut self, value: bool) -> Self {
+        self.unescape_values = value;
+        self
+    }
+}
+
+/// Error returned by strict parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    line: usize,
+    msg: String,
+}
+
+impl ParseError {
+    pub(crate) fn new<S: Into<String>>(line: usize, msg: S) -> ParseError {
+        ParseError {
+            line,
+            msg: msg.into(),
+        }
+    }
+
+    /// Number of offending line, starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Description of the error
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Replace backslashes, ASCII control characters and `;` with `\\` and `\xNN` escapes
+pub fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            // `;` would start a comment
+            c if c.is_ascii_control() || c == ';' => {
+                result.push_str(&format!("\\x{:02X}", c as u32))
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Length of `escape(value)` in bytes
+pub fn escaped_len(value: &str) -> usize {
+    value
+        .chars()
+        .map(|c| match c {
+            '\\' => 2,
+            c if c.is_ascii_control() || c == ';' => 4,
+            c => c.len_utf8(),
+        })
+        .sum()
+}
+
+/// Decode escapes produced by `escape()`, keeping unknown escapes as is
+pub fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find('\\') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with("\\\\") {
+            result.push('\\');
+            rest = &rest[2..];
+        } else if let Some(c) = rest
+            .get(2..4)
+            .filter(|_| rest.starts_with("\\x"))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .filter(u8::is_ascii)
+        {
+            result.push(c as char);
+            rest = &rest[4..];
+        } else {
+            result.push('\\');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Split comma-separated list with CSV-style quoting: fields in double quotes may contain
+/// commas, and `""` inside them stands for a literal quote. Whitespace around fields is trimmed.
+/// Returns `None` for unterminated quotes or text after a closing quote.
+pub fn split_csv(value: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut chars = value.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut field = String::new();
+        let mut last = true;
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    '"' => break,
+                    c => field.push(c),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                Some(',') => last = false,
+                Some(_) => return None,
+                None => (),
+            }
+        } else {
+            for c in chars.by_ref() {
+                if c == ',' {
+                    last = false;
+                    break;
+                }
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if last {
+            return Some(fields);
+        }
+    }
+}
+
+/// Split JSON array like `[1, 2]` or `["a", "b"]` into elements, decoding quoted strings
+///
+/// Unquoted elements are taken verbatim without surrounding whitespace. Returns `None` if the
+/// brackets are missing, an element is empty or a string is malformed.
+pub fn split_json_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut elements = Vec::new();
+    if inner.trim().is_empty() {
+        return Some(elements);
+    }
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut element = String::new();
+        let mut last = true;
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => element.push(json_escape(&mut chars)?),
+                    c if c.is_control() => return None,
+                    c => element.push(c),
+                }
+            }
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                Some(',') => last = false,
+                Some(_) => return None,
+                None => (),
+            }
+        } else {
+            for c in chars.by_ref() {
+                if c == ',' {
+                    last = false;
+                    break;
+                }
+                element.push(c);
+            }
+            element.truncate(element.trim_end().len());
+            if element.is_empty() {
+                return None;
+            }
+        }
+        elements.push(element);
+        if last {
+            return Some(elements);
+        }
+    }
+}
+
+/// Decode JSON escape sequence following a backslash
+fn json_escape<I: Iterator<Item = char>>(chars: &mut I) -> Option<char> {
+    let code = |chars: &mut I| -> Option<u32> {
+        let hex: String = chars.take(4).collect();
+        if hex.len() != 4 {
+            return None;
+        }
+        u32::from_str_radix(&hex, 16).ok()
+    };
+    match chars.next()? {
+        c @ ('"' | '\\' | '/') => Some(c),
+        'b' => Some('\u{8}'),
+        'f' => Some('\u{c}'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'u' => {
+            let high = code(chars)?;
+            if (0xd800..0xdc00).contains(&high) {
+                // surrogate pair
+                if chars.next()? != '\\' || chars.next()? != 'u' {
+                    return None;
+                }
+                let low = code(chars)?;
+                if !(0xdc00..0xe000).contains(&low) {
+                    return None;
+                }
+                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+            } else {
+                char::from_u32(high)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Part of list separator escaped in elements: the separator without surrounding whitespace,
+/// so `, ` and `,` lists escape commas alike
+fn separator_unit(sep: &str) -> &str {
+    match sep.trim() {
+        "" => sep,
+        unit => unit,
+    }
+}
+
+/// Escape backslashes and occurrences of separator in list element with backslash
+pub fn escape_element<'a>(element: &'a str, sep: &str) -> Cow<'a, str> {
+    let unit = separator_unit(sep);
+    if unit.is_empty() || !(element.contains(unit) || element.contains('\\')) {
+        return Cow::Borrowed(element);
+    }
+    let element = element.replace('\\', "\\\\");
+    Cow::Owned(element.replace(unit, &format!("\\{}", unit)))
+}
+
+/// Decode backslashes and separators escaped by `escape_element()`; other backslashes are
+/// kept as they are
+pub fn unescape_element<'a>(element: &'a str, sep: &str) -> Cow<'a, str> {
+    let unit = separator_unit(sep);
+    if unit.is_empty() || !element.contains('\\') {
+        return Cow::Borrowed(element);
+    }
+    let mut result = String::with_capacity(element.len());
+    let mut rest = element;
+    while let Some(pos) = rest.find('\\') {
+        result.push_str(&rest[..pos]);
+        let escaped = &rest[pos + 1..];
+        if let Some(next) = escaped.strip_prefix('\\') {
+            result.push('\\');
+            rest = next;
+        } else if let Some(next) = escaped.strip_prefix(unit) {
+            result.push_str(unit);
+            rest = next;
+        } else {
+            result.push('\\');
+            rest = escaped;
+        }
+    }
+    result.push_str(rest);
+    Cow::Owned(result)
+}
+
+/// Split list value by separators not escaped with backslash, i.e. not preceded by an odd
+/// number of backslashes; an empty separator yields the whole value
+pub fn split_unescaped<'a>(value: &'a str, sep: &'a str) -> impl Iterator<Item = &'a str> {
+    let mut rest = Some(value);
+    std::iter::from_fn(move || {
+        let value = rest?;
+        let mut start = 0;
+        while let Some(pos) = value[start..].find(sep).filter(|_| !sep.is_empty()) {
+            let pos = start + pos;
+            let backslashes = value[..pos].len() - value[..pos].trim_end_matches('\\').len();
+            if backslashes % 2 == 0 {
+                rest = Some(&value[pos + sep.len()..]);
+                return Some(&value[..pos]);
+            }
+            start = pos + sep.len();
+        }
+        rest = None;
+        Some(value)
+    })
+}
+
+/// Escape key containing `=` as `\=`, so it isn't taken as separator, and its backslashes as
+/// `\\`; other keys are kept as they are
+pub fn escape_key<'a>(key: &'a str) -> Cow<'a, str> {
+    if key.contains('=') {
+        escape_element(key, "=")
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Decode key escaped by `escape_key()`; keys without `\=` are kept as they are
+pub fn unescape_key<'a>(key: &'a str) -> Cow<'a, str> {
+    if key.contains("\\=") {
+        unescape_element(key, "=")
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Text of comment following content of the line, if any
+pub fn inline_comment(line: &str) -> Option<String> {
+    line.split_once(';')
+        .map(|(_, comment)| comment.trim().to_owned())
+        .filter(|comment| !comment.is_empty())
+}
+
+/// Number of double quotes to write on both sides of value, so it survives parsing: one if it
+/// starts or ends with whitespace, two if `unquote_value()` would strip quotes it already has.
+/// With `escaped` set, ASCII control characters don't count as whitespace, as they are written
+/// escaped.
+pub fn quote_level(value: &str, escaped: bool) -> usize {
+    let is_space = |c: char| c.is_whitespace() && !(escaped && c.is_ascii_control());
+    let has_space = |value: &str| value.starts_with(is_space) || value.ends_with(is_space);
+    if has_space(value) {
+        return 1;
+    }
+    let inner = value.strip_prefix('"').and_then(|x| x.strip_suffix('"'));
+    if inner.map_or(false, has_space) || is_double_quoted(value) {
+        return 2;
+    }
+    0
+}
+
+/// Check if value starts and ends with three double quotes, not overlapping
+fn is_double_quoted(value: &str) -> bool {
+    value.len() >= 6 && value.starts_with("\"\"\"") && value.ends_with("\"\"\"")
+}
+
+/// Strip quotes written by Ini: two around value starting and ending with `"""`, or one
+/// around value with leading or trailing whitespace
+fn unquote_value(value: &str) -> &str {
+    if is_double_quoted(value) {
+        return &value[2..value.len() - 2];
+    }
+    match value.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+        Some(inner) if quote_level(inner, false) == 1 => inner,
+        _ => value,
+    }
+}
+
+/// Position of the first `=` not escaped with backslash
+fn find_separator(content: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in content.char_indices() {
+        match c {
+            '=' if !escaped => return Some(i),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Parse trimmed line starting with `[`
+///
+/// The header ends at the first `]` followed only by whitespace or a comment, any other text
+/// after `]` is an error. Only the outer brackets are stripped, so `[[a]]` is section `[a]`
+/// and `[db;primary]` is section `db;primary`.
+fn parse_header(header: &str) -> Parsed {
+    let end = header.match_indices(']').map(|(i, _)| i).find(|&i| {
+        let rest = header[i + 1..].trim_start();
+        rest.is_empty() || rest.starts_with(';')
+    });
+    if let Some(end) = end {
+        return Parsed::Section(header[1..end].trim().to_owned());
+    }
+    match header.rfind(']') {
+        Some(end) => {
+            let rest = header[end + 1..].split(';').next().unwrap_or("");
+            Parsed::Error(format!(
+                "unexpected text after section header: {}",
+                rest.trim()
+            ))
+        }
+        None => Parsed::Error("incorrect section syntax".to_owned()),
+    }
+}
+
 #[derive(Debug)]
 pub enum Parsed {
     Error(String),
     Empty,
+    Comment(String),
     Section(String),
     Value(String, String), /* Vector(String, Vec<String>), impossible, because OrderedHashMap field has type String, not Vec */
 }
 
-pub fn parse_line(line: &str) -> Parsed {
+pub fn parse_line(line: &str, options: &ParseOptions) -> Parsed {
+    // section header is recognized before stripping comments, so it may contain `;`
+    let header = line.trim();
+    if header.starts_with('[') {
+        return parse_header(header);
+    }
     let content = match line.split(';').next() {
         Some(value) => value.trim(),
         None => return Parsed::Empty,
     };
     if content.is_empty() {
-        return Parsed::Empty;
+        return match line.trim_start().strip_prefix(';') {
+            Some(comment) => Parsed::Comment(comment.trim().to_owned()),
+            None => Parsed::Empty,
+        };
     }
     // add checks for content
-    if content.starts_with('[') {
-        if content.ends_with(']') {
-            let section_name = content.trim_matches(|c| c == '[' || c == ']').to_owned();
-            return Parsed::Section(section_name);
-        } else {
-            return Parsed::Error("incorrect section syntax".to_owned());
-        }
-    } else if content.contains('=') {
-        let mut pair = content.splitn(2, '=').map(|s| s.trim());
-        // if key is None => error
-        let key = match pair.next() {
-            Some(value) => value.to_owned(),
-            None => return Parsed::Error("key is None".to_owned()),
+    if options.unquote_keys && content.starts_with(['"', '\'']) {
+        let quote = &content[..1];
+        let quoted = &content[1..];
+        let (key, rest) = match quoted.find(quote) {
+            Some(end) => (&quoted[..end], quoted[end + 1..].trim_start()),
+            None => return Parsed::Error("unterminated quoted key".to_owned()),
         };
-        // if value is None => empty string
-        let value = match pair.next() {
-            Some(value) => value.to_owned(),
-            None => "".to_owned(),
+        if key.is_empty() {
+            return Parsed::Error("empty key".to_owned());
+        }
+        let separator = options.separator.as_deref().unwrap_or("=");
+        return match rest.strip_prefix(separator) {
+            Some(value) => Parsed::Value(key.to_owned(), unquote_value(value.trim()).to_owned()),
+            None => Parsed::Error("incorrect syntax".to_owned()),
         };
+    }
+    let found = match &options.separator {
+        Some(separator) => content
+            .find(separator.as_str())
+            .map(|pos| (pos, separator.len())),
+        None => find_separator(content).map(|pos| (pos, 1)),
+    };
+    if let Some((pos, len)) = found {
+        let key = content[..pos].trim();
+        // in keys with `\=`, it stands for literal `=` and `\\` for `\`
+        let key = match options.separator {
+            Some(_) => key.to_owned(),
+            None => unescape_key(key).into_owned(),
+        };
+        let value = unquote_value(content[pos + len..].trim()).to_owned();
         if key.is_empty() {
             return Parsed::Error("empty key".to_owned());
         }
         return Parsed::Value(key, value);
+    } else if options.whitespace_separator {
+        let mut pair = content.splitn(2, char::is_whitespace);
+        let key = pair.next().unwrap_or("").to_owned();
+        let value = unquote_value(pair.next().unwrap_or("").trim()).to_owned();
+        return Parsed::Value(key, value);
     }
     Parsed::Error("incorrect syntax".to_owned())
 }
 
+/// Parse line of config with indented sub-keys, keeping track of the current `parent` key
+///
+/// An unindented line `name:` starts a block, whose indented lines `key: value` or
+/// `key = value` become key `name.key`. Any other unindented line ends the block, blank and
+/// comment lines don't. Blocks can't be nested further.
+pub fn parse_indented_line(
+    line: &str,
+    parent: &mut Option<String>,
+    options: &ParseOptions,
+) -> Parsed {
+    let content = line.split(';').next().unwrap_or("").trim();
+    if content.is_empty() {
+        return parse_line(line, options);
+    }
+    if !line.starts_with(char::is_whitespace) {
+        *parent = None;
+        return match content.strip_suffix(':') {
+            Some(name) if !name.starts_with('[') && find_separator(name).is_none() => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Parsed::Error("empty key".to_owned());
+                }
+                *parent = Some(name.to_owned());
+                Parsed::Empty
+            }
+            _ => parse_line(line, options),
+        };
+    }
+    let parent = match parent {
+        Some(parent) => parent,
+        None => return parse_line(line, options),
+    };
+    let colon = content.find(':');
+    let parsed = match colon {
+        Some(pos) if find_separator(content).map_or(true, |eq| pos < eq) => {
+            if pos + 1 == content.len() {
+                return Parsed::Error("keys can be nested only two levels deep".to_owned());
+            }
+            let key = content[..pos].trim();
+            if key.is_empty() {
+                return Parsed::Error("empty key".to_owned());
+            }
+            let value = unquote_value(content[pos + 1..].trim());
+            Parsed::Value(key.to_owned(), value.to_owned())
+        }
+        _ => parse_line(line, options),
+    };
+    match parsed {
+        Parsed::Value(key, value) => Parsed::Value(format!("{}.{}", parent, key), value),
+        other => other,
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod test {
     use super::*;
 
     #[test]
     fn test_comment() {
-        match parse_line(";------") {
+        match parse_line(";------", &ParseOptions::new()) {
+            Parsed::Comment(text) => assert_eq!(text, String::from("------")),
+            _ => assert!(false),
+        }
+        match parse_line("   ", &ParseOptions::new()) {
             Parsed::Empty => assert!(true),
             _ => assert!(false),
         }
     }
 
+    #[test]
+    fn test_custom_separator() {
+        let options = ParseOptions::new().separator("==");
+        match parse_line("key == a = b", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("key"));
+                assert_eq!(text, String::from("a = b"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("key = value", &options) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_separator() {
+        let _ = ParseOptions::new().separator("");
+    }
+
     #[test]
     fn test_entry() {
-        match parse_line("name1 = 100 ; comment") {
+        match parse_line("name1 = 100 ; comment", &ParseOptions::new()) {
             Parsed::Value(name, text) => {
                 assert_eq!(name, String::from("name1"));
                 assert_eq!(text, String::from("100"));
@@ -67,7 +686,7 @@ mod test {
 
     #[test]
     fn test_weird_name() {
-        match parse_line("_.,:(){}-#@&*| = 100") {
+        match parse_line("_.,:(){}-#@&*| = 100", &ParseOptions::new()) {
             Parsed::Value(name, text) => {
                 assert_eq!(name, String::from("_.,:(){}-#@&*|"));
                 assert_eq!(text, String::from("100"));
@@ -78,7 +697,7 @@ mod test {
 
     #[test]
     fn test_text_entry() {
-        match parse_line("text_name = hello world!") {
+        match parse_line("text_name = hello world!", &ParseOptions::new()) {
             Parsed::Value(name, text) => {
                 assert_eq!(name, String::from("text_name"));
                 assert_eq!(text, String::from("hello world!"));
@@ -89,7 +708,269 @@ mod test {
 
     #[test]
     fn test_incorrect_token() {
-        match parse_line("[section = 1, 2 = value") {
+        match parse_line("[section = 1, 2 = value", &ParseOptions::new()) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_section_trailing_text() {
+        match parse_line("[s]   ", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::from("s")),
+            _ => assert!(false),
+        }
+        match parse_line("[s] junk", &ParseOptions::new()) {
+            Parsed::Error(msg) => assert_eq!(msg, "unexpected text after section header: junk"),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_section_inner_brackets() {
+        match parse_line("[[a]]", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::from("[a]")),
+            _ => assert!(false),
+        }
+        match parse_line("[a]b]", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::from("a]b")),
+            _ => assert!(false),
+        }
+        match parse_line("[]", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::new()),
+            _ => assert!(false),
+        }
+        match parse_line("[", &ParseOptions::new()) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_section_with_semicolon() {
+        match parse_line("[db;primary]", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::from("db;primary")),
+            _ => assert!(false),
+        }
+        match parse_line("  [db;primary] ; comment ]", &ParseOptions::new()) {
+            Parsed::Section(name) => assert_eq!(name, String::from("db;primary")),
+            _ => assert!(false),
+        }
+        match parse_line("[db] junk ; comment", &ParseOptions::new()) {
+            Parsed::Error(msg) => assert_eq!(msg, "unexpected text after section header: junk"),
+            _ => assert!(false),
+        }
+        match parse_line("[db;primary", &ParseOptions::new()) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_whitespace_separator() {
+        let options = ParseOptions::new().whitespace_separator(true);
+        match parse_line("key\tvalue", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("key"));
+                assert_eq!(text, String::from("value"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("key value with spaces", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("key"));
+                assert_eq!(text, String::from("value with spaces"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("key = value with spaces", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("key"));
+                assert_eq!(text, String::from("value with spaces"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("key value", &ParseOptions::new()) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_quoted_key() {
+        let options = ParseOptions::new().unquote_keys(true);
+        match parse_line("\"a b\" = 1", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("a b"));
+                assert_eq!(text, String::from("1"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("'x=y'=2", &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("x=y"));
+                assert_eq!(text, String::from("2"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line("\"a b\" = 1", &ParseOptions::new()) {
+            Parsed::Value(name, _) => assert_eq!(name, String::from("\"a b\"")),
+            _ => assert!(false),
+        }
+        match parse_line("\"a b = 1", &options) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+        match parse_line("\"a\" b = 1", &options) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+        match parse_line("\"\" = 1", &options) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_inline_comment() {
+        assert_eq!(inline_comment("port = 80 ; prod"), Some("prod".to_owned()));
+        assert_eq!(inline_comment("port = 80 ;"), None);
+        assert_eq!(inline_comment("port = 80"), None);
+    }
+
+    #[test]
+    fn test_split_csv() {
+        assert_eq!(
+            split_csv(r#""Smith, John", "Doe, Jane" , plain"#).unwrap(),
+            ["Smith, John", "Doe, Jane", "plain"]
+        );
+        assert_eq!(
+            split_csv(r#""say ""hi""", """", a b,"#).unwrap(),
+            [r#"say "hi""#, "\"", "a b", ""]
+        );
+        assert_eq!(split_csv("").unwrap(), [""]);
+        assert_eq!(split_csv(r#""open, end"#), None);
+        assert_eq!(split_csv(r#""a" b, c"#), None);
+    }
+
+    #[test]
+    fn test_escaped_element() {
+        assert_eq!(escape_element("x,y", ", "), r"x\,y");
+        assert_eq!(escape_element("x|y", " | "), r"x\|y");
+        assert_eq!(escape_element("x y", " "), r"x\ y");
+        assert_eq!(unescape_element(r"x\,y", ","), "x,y");
+        assert_eq!(escape_element(r"C:\dir\", ","), r"C:\\dir\\");
+        assert_eq!(unescape_element(r"C:\\dir\\", ","), r"C:\dir\");
+        assert_eq!(unescape_element(r"C:\dir", ","), r"C:\dir");
+        let elements: Vec<&str> = split_unescaped(r"a\,b, c,,d\,", ",").collect();
+        assert_eq!(elements, [r"a\,b", " c", "", r"d\,"]);
+        let elements: Vec<&str> = split_unescaped(r"a\\,b\\\,c", ",").collect();
+        assert_eq!(elements, [r"a\\", r"b\\\,c"]);
+        let elements: Vec<&str> = split_unescaped("a,b", "").collect();
+        assert_eq!(elements, ["a,b"]);
+    }
+
+    #[test]
+    fn test_quoted_value() {
+        match parse_line("key = \"  x  \"", &ParseOptions::new()) {
+            Parsed::Value(_, text) => assert_eq!(text, String::from("  x  ")),
+            _ => assert!(false),
+        }
+        match parse_line("key = \"x\"", &ParseOptions::new()) {
+            Parsed::Value(_, text) => assert_eq!(text, String::from("\"x\"")),
+            _ => assert!(false),
+        }
+        match parse_line("key = \"", &ParseOptions::new()) {
+            Parsed::Value(_, text) => assert_eq!(text, String::from("\"")),
+            _ => assert!(false),
+        }
+        assert_eq!(quote_level(" x", false), 1);
+        assert_eq!(quote_level("\t", false), 1);
+        assert_eq!(quote_level("\tx", true), 0);
+        assert_eq!(quote_level("", false), 0);
+        assert_eq!(quote_level("\"x\"", false), 0);
+        assert_eq!(quote_level("\"", false), 0);
+        assert_eq!(quote_level("\"\", \"\"", false), 0);
+        assert_eq!(quote_level("\" x \"", false), 2);
+        assert_eq!(quote_level("\"\"\"x\"\"\"", false), 2);
+        assert_eq!(quote_level("\"\"\"\"\"", false), 0);
+        match parse_line("key = \"\"\" x \"\"\"", &ParseOptions::new()) {
+            Parsed::Value(_, text) => assert_eq!(text, "\" x \""),
+            _ => assert!(false),
+        }
+        match parse_line("key = \"\", \"a\", \"\"", &ParseOptions::new()) {
+            Parsed::Value(_, text) => assert_eq!(text, "\"\", \"a\", \"\""),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_indented_line() {
+        let options = ParseOptions::new();
+        let mut parent = None;
+        match parse_indented_line("server:", &mut parent, &options) {
+            Parsed::Empty => assert!(true),
+            _ => assert!(false),
+        }
+        assert_eq!(parent.as_deref(), Some("server"));
+        match parse_indented_line("  url: http://a:80", &mut parent, &options) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, "server.url");
+                assert_eq!(text, "http://a:80");
+            }
+            _ => assert!(false),
+        }
+        match parse_indented_line("  tls:", &mut parent, &options) {
+            Parsed::Error(_) => assert!(true),
+            _ => assert!(false),
+        }
+        match parse_indented_line("x = 1", &mut parent, &options) {
+            Parsed::Value(name, _) => assert_eq!(name, "x"),
+            _ => assert!(false),
+        }
+        assert_eq!(parent, None);
+    }
+
+    #[test]
+    fn test_split_json_array() {
+        assert_eq!(
+            split_json_array(r#"[1, "a, \"b\"", "é😀\n" ]"#).unwrap(),
+            ["1", "a, \"b\"", "é😀\n"]
+        );
+        assert_eq!(split_json_array("[ ]").unwrap(), Vec::<String>::new());
+        assert_eq!(split_json_array("1, 2"), None);
+        assert_eq!(split_json_array("[1,]"), None);
+        assert_eq!(split_json_array(r#"["a" b]"#), None);
+        assert_eq!(split_json_array(r#"["\x"]"#), None);
+        assert_eq!(split_json_array(r#"["a]"#), None);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\tb\0c\\d"), r"a\x09b\x00c\\d");
+        assert_eq!(escape("a ; b"), r"a \x3B b");
+        assert_eq!(escaped_len("a\tb\0c\\d;é"), escape("a\tb\0c\\d;é").len());
+        assert_eq!(unescape(r"a\x09b\x00c\\d"), "a\tb\0c\\d");
+        assert_eq!(unescape(r"\xZZ \xFF \q \"), r"\xZZ \xFF \q \");
+    }
+
+    #[test]
+    fn test_escaped_key() {
+        match parse_line(r"a\= = 1", &ParseOptions::new()) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("a="));
+                assert_eq!(text, String::from("1"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line(r"x\=y=z = 2", &ParseOptions::new()) {
+            Parsed::Value(name, text) => {
+                assert_eq!(name, String::from("x=y"));
+                assert_eq!(text, String::from("z = 2"));
+            }
+            _ => assert!(false),
+        }
+        match parse_line(r"a\= 1", &ParseOptions::new()) {
             Parsed::Error(_) => assert!(true),
             _ => assert!(false),
         }
@@ -97,7 +978,7 @@ mod test {
 
     #[test]
     fn test_incorrect_key_value_line() {
-        match parse_line("= 3") {
+        match parse_line("= 3", &ParseOptions::new()) {
             Parsed::Error(_) => assert!(true),
             _ => assert!(false),
         }